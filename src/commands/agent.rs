@@ -0,0 +1,285 @@
+use anyhow::{anyhow, Ok};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs::{self, DirBuilder};
+use std::io::{stdin, BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use zeroize::Zeroize;
+
+use super::entries::{env_output, get_output, list_output};
+use crate::database::operations::{resolve_database_path, Database};
+use crate::database::structures::DatabaseFile;
+use crate::{AgentStart, AgentStop};
+
+/// How long a client may take to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Requests the CLI sends to a running agent, one JSON object per line
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum AgentRequest {
+    Get { entry: String, field: Option<String> },
+    List { fields: bool },
+    Env,
+    Stop,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AgentResponse {
+    output: Option<String>,
+    error: Option<String>,
+}
+
+/// Unlocks the vault once and starts the agent in the background
+pub fn start_agent(args: AgentStart) -> Result<(), anyhow::Error> {
+    let path = resolve_database_path(&args.path, &args.database_name)?;
+    let socket = socket_path(&path)?;
+    if UnixStream::connect(&socket).is_ok() {
+        return Err(anyhow!("an agent is already running for {}", path.display()));
+    }
+
+    let password = rpassword::prompt_password("Please enter the password:")?;
+    // fail here instead of in the background if the password is wrong
+    DatabaseFile::load(&path, &password)?;
+
+    let mut child = Command::new(env::current_exe()?)
+        .args(["agent", "serve", "-d", &args.database_name])
+        .arg("-p")
+        .arg(&path)
+        .arg("--ttl")
+        .arg(args.ttl.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // own process group so the agent survives closing the terminal
+        .process_group(0)
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(format!("{}\n", password).as_bytes())?;
+
+    for _ in 0..100 {
+        if UnixStream::connect(&socket).is_ok() {
+            println!(
+                "Agent started for {} (pid {}), it stops after {} seconds",
+                path.display(),
+                child.id(),
+                args.ttl
+            );
+            return Ok(());
+        }
+        if let Some(status) = child.try_wait()? {
+            return Err(anyhow!("the agent stopped unexpectedly: {}", status));
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    Err(anyhow!("the agent didn't start in time"))
+}
+
+pub fn stop_agent(args: AgentStop) -> Result<(), anyhow::Error> {
+    match request_from_agent(&args.path, &args.database_name, &AgentRequest::Stop) {
+        Some(result) => {
+            result?;
+            println!("Agent stopped");
+            Ok(())
+        }
+        None => Err(anyhow!("no agent is running for this vault")),
+    }
+}
+
+/// Runs the agent in the foreground, the password is read from stdin
+pub fn serve_agent(args: AgentStart) -> Result<(), anyhow::Error> {
+    let path = resolve_database_path(&args.path, &args.database_name)?;
+    let mut password = String::new();
+    stdin().read_line(&mut password)?;
+    let mut db: DatabaseFile = *DatabaseFile::load(&path, password.trim_end_matches('\n'))?;
+    let mut loaded_at = modified_at(&path)?;
+    password.zeroize();
+
+    let socket = socket_path(&path)?;
+    if socket.exists() {
+        fs::remove_file(&socket)?;
+    }
+    let listener = UnixListener::bind(&socket)?;
+    fs::set_permissions(&socket, fs::Permissions::from_mode(0o600))?;
+
+    let ttl_socket = socket.clone();
+    let ttl = args.ttl;
+    thread::spawn(move || {
+        thread::sleep(Duration::from_secs(ttl));
+        let _ = fs::remove_file(&ttl_socket);
+        std::process::exit(0);
+    });
+
+    // SAFETY: getuid has no preconditions and can't fail
+    let own_uid = unsafe { libc::getuid() };
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            std::result::Result::Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        match peer_uid(&stream) {
+            std::result::Result::Ok(uid) if uid == own_uid => {}
+            // other users are not allowed to talk to the agent
+            _ => continue,
+        }
+
+        // an idle client would block every other caller of the single threaded loop
+        if stream.set_read_timeout(Some(REQUEST_TIMEOUT)).is_err() {
+            continue;
+        }
+        let mut line = String::new();
+        if BufReader::new(&stream).read_line(&mut line).is_err() {
+            continue;
+        }
+        let request: AgentRequest = match serde_json::from_str(&line) {
+            std::result::Result::Ok(request) => request,
+            Err(_) => continue,
+        };
+
+        // pick up changes made by other vault commands in the meantime. If the file can't be read, e.g. because
+        // it is being saved right now, the loaded vault is kept and the next request tries again
+        match reload_if_changed(&path, &db.password, loaded_at) {
+            std::result::Result::Ok(Some((reloaded, modified))) => {
+                db = reloaded;
+                loaded_at = modified;
+            }
+            std::result::Result::Ok(None) => {}
+            Err(err) => eprintln!("Reloading the vault failed, answering from the loaded one: {}", err),
+        }
+
+        let result = match &request {
+            AgentRequest::Get { entry, field } => get_output(&db, entry, field.as_deref()),
+            AgentRequest::List { fields } => Ok(list_output(&db, *fields)),
+            AgentRequest::Env => Ok(env_output(&db)),
+            AgentRequest::Stop => Ok(String::new()),
+        };
+        let response = match result {
+            std::result::Result::Ok(output) => AgentResponse {
+                output: Some(output),
+                error: None,
+            },
+            Err(err) => AgentResponse {
+                output: None,
+                error: Some(err.to_string()),
+            },
+        };
+        let _ = writeln!(stream, "{}", serde_json::to_string(&response)?);
+
+        if let AgentRequest::Stop = request {
+            fs::remove_file(&socket)?;
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// Sends the request to the agent of the vault, returns None if no agent is running
+pub fn request_from_agent(
+    path: &Option<PathBuf>,
+    database_name: &str,
+    request: &AgentRequest,
+) -> Option<Result<String, anyhow::Error>> {
+    let path = resolve_database_path(path, database_name).ok()?;
+    let socket = socket_path(&path).ok()?;
+    let mut stream = UnixStream::connect(socket).ok()?;
+    Some(send_request(&mut stream, request))
+}
+
+fn send_request(stream: &mut UnixStream, request: &AgentRequest) -> Result<String, anyhow::Error> {
+    writeln!(stream, "{}", serde_json::to_string(request)?)?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let response: AgentResponse = serde_json::from_str(&line)?;
+    match (response.output, response.error) {
+        (_, Some(error)) => Err(anyhow!(error)),
+        (Some(output), None) => Ok(output),
+        (None, None) => Err(anyhow!("empty response from the agent")),
+    }
+}
+
+/// Every vault gets its own socket inside a directory only the current user can access
+fn socket_path(database_path: &Path) -> Result<PathBuf, anyhow::Error> {
+    // SAFETY: getuid has no preconditions and can't fail
+    let uid = unsafe { libc::getuid() };
+    let directory = match env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) => PathBuf::from(runtime_dir).join("vault"),
+        None => env::temp_dir().join(format!("vault-{}", uid)),
+    };
+    DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&directory)?;
+    let metadata = fs::symlink_metadata(&directory)?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(anyhow!(
+            "{} must be a directory only accessible by the current user",
+            directory.display()
+        ));
+    }
+
+    let canonical_path = fs::canonicalize(database_path)?;
+    let hash = Sha256::digest(canonical_path.to_string_lossy().as_bytes());
+    let name: String = hash[..8].iter().map(|x| format!("{:02x}", x)).collect();
+    Ok(directory.join(format!("agent-{}.sock", name)))
+}
+
+fn modified_at(path: &Path) -> Result<SystemTime, anyhow::Error> {
+    Ok(fs::metadata(path)?.modified()?)
+}
+
+/// The vault and its modification time if the file changed since `loaded_at`
+fn reload_if_changed(
+    path: &Path,
+    password: &str,
+    loaded_at: SystemTime,
+) -> Result<Option<(DatabaseFile, SystemTime)>, anyhow::Error> {
+    let modified = modified_at(path)?;
+    if modified == loaded_at {
+        return Ok(None);
+    }
+    Ok(Some((*DatabaseFile::load(path, password)?, modified)))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> Result<u32, anyhow::Error> {
+    // SAFETY: ucred only contains integers, so all zeros is a valid value
+    let mut credentials: libc::ucred = unsafe { std::mem::zeroed() };
+    let mut length = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: credentials and length point to valid memory of the size passed to getsockopt
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut credentials as *mut libc::ucred as *mut libc::c_void,
+            &mut length,
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(credentials.uid)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> Result<u32, anyhow::Error> {
+    let mut uid: libc::uid_t = 0;
+    let mut gid: libc::gid_t = 0;
+    // SAFETY: uid and gid point to valid memory
+    let result = unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(uid)
+}
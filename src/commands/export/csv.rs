@@ -0,0 +1,42 @@
+use std::path::Path;
+
+use super::write_private_file;
+use crate::database::fields::FieldSlot;
+use crate::database::structures::DatabaseFile;
+
+/// Column names the CSV import of the vault and most password managers detect
+const HEADER: [&str; 9] = [
+    "title", "username", "password", "url", "totp", "notes", "group", "type", "fields",
+];
+
+/// Writes one row per entry, fields only some entry types have are combined in the last column
+pub fn write_csv(db: &DatabaseFile, output: &Path) -> Result<(), anyhow::Error> {
+    let mut writer = ::csv::Writer::from_writer(Vec::new());
+    writer.write_record(HEADER)?;
+    for entry in db.entries.iter() {
+        let mut totp = String::new();
+        let mut fields = Vec::new();
+        for spec in entry.fields() {
+            let value = entry.get_field(spec);
+            match spec.slot {
+                FieldSlot::Extra("totp") => totp = value,
+                FieldSlot::Extra(key) if !value.is_empty() => {
+                    fields.push(format!("{}: {}", key, value))
+                }
+                _ => {}
+            }
+        }
+        writer.write_record([
+            entry.title.as_str(),
+            entry.name.as_str(),
+            entry.value.as_str(),
+            entry.url.as_str(),
+            totp.as_str(),
+            entry.comment.as_str(),
+            entry.group.as_str(),
+            entry.entry_type.key(),
+            fields.join("\n").as_str(),
+        ])?;
+    }
+    write_private_file(output, &writer.into_inner()?)
+}
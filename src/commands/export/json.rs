@@ -0,0 +1,37 @@
+use anyhow::Ok;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::path::Path;
+
+use super::write_private_file;
+use crate::database::structures::{DatabaseFile, PasswordEntry};
+use crate::encryption_and_decryption::argon::encrypt_text;
+use crate::utils::terminal_interactions::prompt_password;
+
+#[derive(Serialize)]
+struct PlainExport<'a> {
+    exported_at: DateTime<Local>,
+    entries: &'a [PasswordEntry],
+}
+
+/// Writes the entries as plain JSON, the password of the vault isn't part of it
+pub fn write_json(db: &DatabaseFile, output: &Path) -> Result<(), anyhow::Error> {
+    let export = PlainExport {
+        exported_at: Local::now(),
+        entries: &db.entries,
+    };
+    write_private_file(output, serde_json::to_string_pretty(&export)?.as_bytes())
+}
+
+/// Writes a copy of the vault encrypted with a new password, it can be opened like any other vault
+pub fn write_encrypted_json(db: &DatabaseFile, output: &Path) -> Result<(), anyhow::Error> {
+    let mut copy = db.clone();
+    copy.password = prompt_password("the password for the export")?;
+    encrypt_text(
+        &serde_json::to_string(&copy)?,
+        output,
+        &copy.password,
+        &copy.kdf,
+    )?;
+    Ok(())
+}
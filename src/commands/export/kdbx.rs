@@ -0,0 +1,70 @@
+use anyhow::{anyhow, Ok};
+use base64::Engine;
+use keepass::db::{GroupId, Value};
+use keepass::{Database, DatabaseKey};
+use std::path::Path;
+
+use super::{keepass_strings, keepass_tags, write_private_file, GroupTree};
+use crate::database::structures::DatabaseFile;
+use crate::utils::terminal_interactions::prompt_password;
+
+/// Writes a KDBX 4 database (AES-256, Argon2) protected by a new password
+pub fn write_kdbx(db: &DatabaseFile, output: &Path) -> Result<(), anyhow::Error> {
+    let mut keepass = Database::new();
+    keepass.meta.generator = Some(String::from("vault"));
+    keepass.meta.database_description = Some(db.config.comment.clone());
+    let root = keepass.root().id();
+    keepass.group_mut(root).expect("the root group exists").name = String::from("Root");
+    add_group(&mut keepass, root, &GroupTree::new(&db.entries))?;
+
+    let password = prompt_password("the password for the KeePass database")?;
+    let mut content = Vec::new();
+    keepass
+        .save(&mut content, DatabaseKey::new().with_password(&password))
+        .map_err(|err| anyhow!("writing the KDBX file failed: {}", err))?;
+    write_private_file(output, &content)
+}
+
+fn add_group(keepass: &mut Database, id: GroupId, tree: &GroupTree) -> Result<(), anyhow::Error> {
+    for entry in tree.entries.iter() {
+        let mut group = keepass
+            .group_mut(id)
+            .expect("groups are created before their entries");
+        let mut keepass_entry = group.add_entry();
+        for string in keepass_strings(entry) {
+            let value = if string.protected {
+                Value::protected(string.value)
+            } else {
+                Value::unprotected(string.value)
+            };
+            keepass_entry.set(string.key, value);
+        }
+        keepass_entry.tags = keepass_tags(entry);
+        if let Some(time) = entry.last_modified_at() {
+            keepass_entry.times.last_modification = Some(time.naive_utc());
+        }
+        for file in entry.files.iter() {
+            let data = base64::engine::general_purpose::STANDARD
+                .decode(&file.content)
+                .map_err(|err| {
+                    anyhow!(
+                        "file '{}' of '{}' is broken: {}",
+                        file.name,
+                        entry.title,
+                        err
+                    )
+                })?;
+            keepass_entry.add_attachment(file.name.clone(), Value::protected(data));
+        }
+    }
+    for (name, child) in tree.children.iter() {
+        let child_id = {
+            let mut group = keepass.group_mut(id).expect("the parent group exists");
+            let mut child_group = group.add_group();
+            child_group.name = name.clone();
+            child_group.id()
+        };
+        add_group(keepass, child_id, child)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use base64::Engine;
+use chrono::Utc;
+use std::path::Path;
+use uuid::Uuid;
+
+use super::{keepass_strings, keepass_tags, write_private_file, GroupTree};
+use crate::database::structures::{DatabaseFile, PasswordEntry};
+
+/// Writes the entries in the XML format of KeePass 2, KeePass and KeePassXC can import it
+pub fn write_keepass_xml(db: &DatabaseFile, output: &Path) -> Result<(), anyhow::Error> {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n");
+    xml.push_str("<KeePassFile>\n");
+    xml.push_str("\t<Meta>\n\t\t<Generator>vault</Generator>\n");
+    xml.push_str(&format!(
+        "\t\t<DatabaseDescription>{}</DatabaseDescription>\n",
+        escape(&db.config.comment)
+    ));
+    xml.push_str("\t</Meta>\n\t<Root>\n");
+    write_group(&mut xml, "Root", &GroupTree::new(&db.entries), 2);
+    xml.push_str("\t</Root>\n</KeePassFile>\n");
+    write_private_file(output, xml.as_bytes())
+}
+
+fn write_group(xml: &mut String, name: &str, tree: &GroupTree, depth: usize) {
+    let indent = "\t".repeat(depth);
+    xml.push_str(&format!("{}<Group>\n", indent));
+    xml.push_str(&format!(
+        "{}\t<UUID>{}</UUID>\n",
+        indent,
+        encode_uuid(&Uuid::new_v4())
+    ));
+    xml.push_str(&format!("{}\t<Name>{}</Name>\n", indent, escape(name)));
+    for entry in tree.entries.iter() {
+        write_entry(xml, entry, depth + 1);
+    }
+    for (name, child) in tree.children.iter() {
+        write_group(xml, name, child, depth + 1);
+    }
+    xml.push_str(&format!("{}</Group>\n", indent));
+}
+
+fn write_entry(xml: &mut String, entry: &PasswordEntry, depth: usize) {
+    let indent = "\t".repeat(depth);
+    let uuid = Uuid::parse_str(&entry.id).unwrap_or_else(|_| Uuid::new_v4());
+    xml.push_str(&format!("{}<Entry>\n", indent));
+    xml.push_str(&format!(
+        "{}\t<UUID>{}</UUID>\n",
+        indent,
+        encode_uuid(&uuid)
+    ));
+    xml.push_str(&format!(
+        "{}\t<Tags>{}</Tags>\n",
+        indent,
+        escape(&keepass_tags(entry).join(";"))
+    ));
+    if let Some(time) = entry.last_modified_at() {
+        xml.push_str(&format!(
+            "{}\t<Times><LastModificationTime>{}</LastModificationTime></Times>\n",
+            indent,
+            time.with_timezone(&Utc).format("%Y-%m-%dT%H:%M:%SZ")
+        ));
+    }
+    for string in keepass_strings(entry) {
+        let protection = if string.protected {
+            " ProtectInMemory=\"True\""
+        } else {
+            ""
+        };
+        xml.push_str(&format!(
+            "{}\t<String><Key>{}</Key><Value{}>{}</Value></String>\n",
+            indent,
+            escape(&string.key),
+            protection,
+            escape(&string.value)
+        ));
+    }
+    // the content of the files is already base64, which is what KeePass expects inline
+    for file in entry.files.iter() {
+        xml.push_str(&format!(
+            "{}\t<Binary><Key>{}</Key><Value>{}</Value></Binary>\n",
+            indent,
+            escape(&file.name),
+            file.content
+        ));
+    }
+    xml.push_str(&format!("{}</Entry>\n", indent));
+}
+
+/// KeePass stores UUIDs as base64 of their bytes
+fn encode_uuid(uuid: &Uuid) -> String {
+    base64::engine::general_purpose::STANDARD.encode(uuid.as_bytes())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
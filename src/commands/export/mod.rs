@@ -0,0 +1,159 @@
+use anyhow::{anyhow, Ok};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use crate::database::fields::FieldSlot;
+use crate::database::lock::VaultLock;
+use crate::database::operations::Database;
+use crate::database::structures::{DatabaseFile, EntryType, PasswordEntry};
+use crate::utils::terminal_interactions::prompt_user;
+use crate::Export;
+
+mod csv;
+mod json;
+mod kdbx;
+mod keepass_xml;
+
+/// Formats `vault export --format` can write
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    /// KeePass database (KDBX 4), encrypted with a new password
+    Kdbx,
+    /// XML in the format of KeePass 2, unencrypted
+    KeepassXml,
+    /// CSV with one row per entry, unencrypted
+    Csv,
+    /// Vault file encrypted with a new password, or plain JSON with --unencrypted
+    Json,
+}
+
+impl ExportFormat {
+    fn is_plaintext(&self) -> bool {
+        matches!(self, ExportFormat::KeepassXml | ExportFormat::Csv)
+    }
+}
+
+/// Writes all entries of the vault to a file which other password managers can read
+pub fn export_entries(mut args: Export) -> Result<(), anyhow::Error> {
+    if args.format.is_plaintext() && !args.unencrypted {
+        return Err(anyhow!(
+            "{} files are not encrypted, pass --unencrypted to confirm that all passwords are written as plain text",
+            args.format
+                .to_possible_value()
+                .expect("formats aren't skipped")
+                .get_name()
+        ));
+    }
+    if args.format == ExportFormat::Kdbx && args.unencrypted {
+        return Err(anyhow!("KDBX files are always encrypted"));
+    }
+    if args.output.exists() {
+        let answer = prompt_user(&format!(
+            "{} already exists, overwrite it? [y/N] ",
+            args.output.display()
+        ))?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            return Ok(());
+        }
+    }
+
+    // the output may be a vault which is open somewhere else
+    let _lock = VaultLock::acquire_for_writing(&args.output)?;
+    let db: DatabaseFile = *Database::new(&mut args.path, args.database_name.as_str())?;
+    match args.format {
+        ExportFormat::Kdbx => kdbx::write_kdbx(&db, &args.output)?,
+        ExportFormat::KeepassXml => keepass_xml::write_keepass_xml(&db, &args.output)?,
+        ExportFormat::Csv => csv::write_csv(&db, &args.output)?,
+        ExportFormat::Json if args.unencrypted => json::write_json(&db, &args.output)?,
+        ExportFormat::Json => json::write_encrypted_json(&db, &args.output)?,
+    }
+    println!(
+        "Exported {} entries to {}",
+        db.entries.len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+/// Plaintext exports are only readable by the current user
+fn write_private_file(path: &Path, content: &[u8]) -> Result<(), anyhow::Error> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    // the mode only applies to new files, an existing file may still be readable by others
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    file.write_all(content)?;
+    Ok(())
+}
+
+/// Groups of the entries as tree, KeePass formats nest their groups
+#[derive(Default)]
+struct GroupTree<'a> {
+    entries: Vec<&'a PasswordEntry>,
+    children: BTreeMap<String, GroupTree<'a>>,
+}
+
+impl<'a> GroupTree<'a> {
+    fn new(entries: &'a [PasswordEntry]) -> GroupTree<'a> {
+        let mut root = GroupTree::default();
+        for entry in entries.iter() {
+            let mut group = &mut root;
+            for name in entry.group.split('/').filter(|x| !x.is_empty()) {
+                group = group.children.entry(name.to_owned()).or_default();
+            }
+            group.entries.push(entry);
+        }
+        root
+    }
+}
+
+/// A string of a KeePass entry
+struct KeepassString {
+    key: String,
+    value: String,
+    protected: bool,
+}
+
+/// Maps the fields of an entry onto the standard strings of KeePass, fields without a standard
+/// string are stored as custom strings named after their label
+fn keepass_strings(entry: &PasswordEntry) -> Vec<KeepassString> {
+    let mut strings = Vec::new();
+    for spec in entry.fields() {
+        let value = entry.get_field(spec);
+        let key = match spec.slot {
+            FieldSlot::Title => "Title".to_owned(),
+            FieldSlot::Name => "UserName".to_owned(),
+            FieldSlot::Value => "Password".to_owned(),
+            FieldSlot::Url => "URL".to_owned(),
+            FieldSlot::Comment => "Notes".to_owned(),
+            // KeePassXC reads otpauth URIs from otp, KeePass 2 only the secret
+            FieldSlot::Extra("totp") if value.starts_with("otpauth://") => "otp".to_owned(),
+            FieldSlot::Extra("totp") => "TimeOtp-Secret-Base32".to_owned(),
+            FieldSlot::Extra(_) => spec.label.to_owned(),
+        };
+        if value.is_empty() && !matches!(spec.slot, FieldSlot::Title | FieldSlot::Value) {
+            continue;
+        }
+        strings.push(KeepassString {
+            key,
+            protected: spec.secret || spec.slot == FieldSlot::Value,
+            value,
+        });
+    }
+    strings
+}
+
+/// Entries which are no passwords are tagged with their type
+fn keepass_tags(entry: &PasswordEntry) -> Vec<String> {
+    if entry.entry_type == EntryType::ClassicPassword {
+        Vec::new()
+    } else {
+        vec![entry.entry_type.key().to_owned()]
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::Ok;
+use chrono::Local;
+use std::collections::HashMap;
+use std::io::{stdin, BufRead};
+
+use crate::database::lock::VaultLock;
+use crate::database::operations::{resolve_database_path, Database};
+use crate::database::structures::{DatabaseFile, EntryType, PasswordEntry};
+use crate::GitCredential;
+
+/// Credential description git sends on stdin, see git-credential(1)
+struct CredentialRequest {
+    protocol: Option<String>,
+    host: String,
+    path: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+/// Implements the git credential helper protocol for `git config credential.helper`
+pub fn git_credential(mut args: GitCredential) -> Result<(), anyhow::Error> {
+    let request = read_request()?;
+    // store and erase write the vault, it must not be open somewhere else meanwhile
+    let _lock = match args.operation.as_str() {
+        "store" | "erase" => {
+            let path = resolve_database_path(&args.path, &args.database_name)?;
+            let lock = VaultLock::acquire_for_writing(&path)?;
+            args.path = Some(path);
+            Some(lock)
+        }
+        _ => None,
+    };
+    match args.operation.as_str() {
+        "get" => {
+            let db: DatabaseFile = *Database::new(&mut args.path, args.database_name.as_str())?;
+            if let Some(entry) = find_matching_entry(&db, &request) {
+                println!("username={}", entry.name);
+                println!("password={}", entry.value);
+            }
+        }
+        "store" => {
+            let (username, password) = match (&request.username, &request.password) {
+                (Some(username), Some(password)) => (username.clone(), password.clone()),
+                _ => return Ok(()),
+            };
+            let mut db: DatabaseFile = *Database::new(&mut args.path, args.database_name.as_str())?;
+            let id = match find_matching_entry(&db, &request) {
+                // git stores after every successful login, the vault is only written on changes
+                Some(entry) if entry.name == username && entry.value == password => return Ok(()),
+                Some(entry) => entry.id.clone(),
+                None => {
+                    let id = db.add_empty_entry();
+                    let entry = db.entries.iter_mut().find(|x| x.id == id).unwrap();
+                    entry.title = format!("{}{}", request.host, format_path(&request.path));
+                    entry.url = format!(
+                        "{}://{}{}",
+                        request.protocol.as_deref().unwrap_or("https"),
+                        request.host,
+                        format_path(&request.path)
+                    );
+                    id
+                }
+            };
+            let entry = db.entries.iter_mut().find(|x| x.id == id).unwrap();
+            entry.name = username;
+            entry.value = password;
+            entry.last_modified = Local::now().to_string();
+            db.save_database(&args.path)?;
+        }
+        "erase" => {
+            let mut db: DatabaseFile = *Database::new(&mut args.path, args.database_name.as_str())?;
+            if let Some(id) = find_matching_entry(&db, &request).map(|x| x.id.clone()) {
+                db.remove_entry_by_id(id);
+                db.save_database(&args.path)?;
+            }
+        }
+        // git asks helpers to ignore operations they don't know
+        _ => {}
+    }
+    Ok(())
+}
+
+fn read_request() -> Result<CredentialRequest, anyhow::Error> {
+    let mut attributes = HashMap::new();
+    for line in stdin().lock().lines() {
+        let line = line?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            attributes.insert(key.to_owned(), value.to_owned());
+        }
+    }
+
+    // callers may only send the url attribute instead of its parts
+    if let Some(url) = attributes.get("url").cloned() {
+        let (protocol, host, path) = split_url(&url);
+        attributes.entry("protocol".to_owned()).or_insert_with(|| protocol.unwrap_or_default());
+        attributes.entry("host".to_owned()).or_insert(host);
+        if !path.is_empty() {
+            attributes.entry("path".to_owned()).or_insert(path);
+        }
+    }
+
+    Ok(CredentialRequest {
+        protocol: attributes.remove("protocol").filter(|x| !x.is_empty()),
+        host: attributes.remove("host").unwrap_or_default().to_lowercase(),
+        path: attributes
+            .remove("path")
+            .map(|x| normalize_path(&x))
+            .filter(|x| !x.is_empty()),
+        username: attributes.remove("username"),
+        password: attributes.remove("password"),
+    })
+}
+
+/// Splits an url like `https://user@github.com/org/repo.git` into protocol, host and path,
+/// the protocol is optional so plain `github.com` works as well
+fn split_url(url: &str) -> (Option<String>, String, String) {
+    let (protocol, rest) = match url.split_once("://") {
+        Some((protocol, rest)) => (Some(protocol.to_lowercase()), rest),
+        None => (None, url),
+    };
+    let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    (protocol, host.to_lowercase(), normalize_path(path))
+}
+
+/// `org/repo` and `/org/repo.git` describe the same repository
+fn normalize_path(path: &str) -> String {
+    let path = path.trim_matches('/');
+    path.strip_suffix(".git").unwrap_or(path).to_owned()
+}
+
+fn format_path(path: &Option<String>) -> String {
+    match path {
+        Some(path) => format!("/{}", path),
+        None => String::new(),
+    }
+}
+
+/// Finds the password entry matching host and path, entries with the longest matching path win.
+/// A request without a path never gets the credentials of a single repository
+fn find_matching_entry<'a>(
+    db: &'a DatabaseFile,
+    request: &CredentialRequest,
+) -> Option<&'a PasswordEntry> {
+    db.entries
+        .iter()
+        .filter(|x| x.entry_type == EntryType::ClassicPassword && !x.url.is_empty())
+        .filter(|x| match &request.username {
+            Some(username) => &x.name == username,
+            None => true,
+        })
+        .filter_map(|entry| {
+            let (protocol, host, path) = split_url(&entry.url);
+            if host != request.host {
+                return None;
+            }
+            if let (Some(protocol), Some(requested)) = (&protocol, &request.protocol) {
+                if protocol != requested {
+                    return None;
+                }
+            }
+            match &request.path {
+                Some(requested)
+                    if path.is_empty()
+                        || requested == &path
+                        || requested.starts_with(&format!("{}/", path)) =>
+                {
+                    Some((path.len() + 1, entry))
+                }
+                Some(_) => None,
+                // without a path only entries for the whole host match
+                None if path.is_empty() => Some((1, entry)),
+                None => None,
+            }
+        })
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, entry)| entry)
+}
@@ -0,0 +1,288 @@
+use anyhow::{anyhow, Ok};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use super::{append_line, new_entry, set_field_or_comment, skip_secret, ImportResult};
+use crate::database::structures::{EntryType, PasswordEntry};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    folders: Vec<Folder>,
+    #[serde(default)]
+    collections: Vec<Folder>,
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Folder {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    #[serde(rename = "type")]
+    item_type: u8,
+    name: Option<String>,
+    notes: Option<String>,
+    folder_id: Option<String>,
+    #[serde(default)]
+    collection_ids: Option<Vec<String>>,
+    #[serde(default)]
+    fields: Vec<CustomField>,
+    login: Option<Login>,
+    card: Option<Card>,
+    identity: Option<Identity>,
+    ssh_key: Option<SshKey>,
+    revision_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CustomField {
+    name: Option<String>,
+    value: Option<String>,
+    #[serde(rename = "type")]
+    field_type: u8,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    username: Option<String>,
+    password: Option<String>,
+    totp: Option<String>,
+    #[serde(default)]
+    uris: Option<Vec<Uri>>,
+}
+
+#[derive(Deserialize)]
+struct Uri {
+    uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Card {
+    cardholder_name: Option<String>,
+    brand: Option<String>,
+    number: Option<String>,
+    exp_month: Option<String>,
+    exp_year: Option<String>,
+    code: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Identity {
+    title: Option<String>,
+    first_name: Option<String>,
+    middle_name: Option<String>,
+    last_name: Option<String>,
+    address1: Option<String>,
+    address2: Option<String>,
+    address3: Option<String>,
+    city: Option<String>,
+    state: Option<String>,
+    postal_code: Option<String>,
+    country: Option<String>,
+    company: Option<String>,
+    email: Option<String>,
+    phone: Option<String>,
+    ssn: Option<String>,
+    username: Option<String>,
+    passport_number: Option<String>,
+    license_number: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SshKey {
+    private_key: Option<String>,
+    public_key: Option<String>,
+}
+
+// Item types of the Bitwarden export
+const LOGIN: u8 = 1;
+const SECURE_NOTE: u8 = 2;
+const CARD: u8 = 3;
+const IDENTITY: u8 = 4;
+const SSH_KEY: u8 = 5;
+
+/// Custom field type whose value is masked like a password
+const HIDDEN_FIELD: u8 = 1;
+/// Custom field type which only points to another field of the item
+const LINKED_FIELD: u8 = 3;
+
+/// Reads the unencrypted JSON export of Bitwarden (Tools > Export vault > .json)
+pub fn read_bitwarden(file: &Path) -> Result<ImportResult, anyhow::Error> {
+    let export: Export = serde_json::from_str(&fs::read_to_string(file)?)
+        .map_err(|err| anyhow!("{} is not a Bitwarden JSON export: {}", file.display(), err))?;
+    if export.encrypted {
+        return Err(anyhow!(
+            "encrypted Bitwarden exports are not supported, please export the vault as unencrypted .json"
+        ));
+    }
+
+    let mut result = ImportResult {
+        entries: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for item in export.items.iter() {
+        let folder_id = item.folder_id.as_ref().or_else(|| {
+            // organization exports use collections instead of folders
+            item.collection_ids.as_ref().and_then(|x| x.first())
+        });
+        let group = folder_id
+            .and_then(|id| {
+                export
+                    .folders
+                    .iter()
+                    .chain(export.collections.iter())
+                    .find(|x| &x.id == id)
+            })
+            .map(|x| x.name.clone())
+            .unwrap_or_default();
+        match convert_item(item, &mut result.skipped) {
+            Some(mut entry) => {
+                entry.group = group;
+                result.entries.push(entry);
+            }
+            None => result.skipped.push(format!(
+                "'{}' has the unknown item type {}",
+                item.name.as_deref().unwrap_or_default(),
+                item.item_type
+            )),
+        }
+    }
+    Ok(result)
+}
+
+fn convert_item(item: &Item, skipped: &mut Vec<String>) -> Option<PasswordEntry> {
+    let mut entry = new_entry();
+    entry.title = item.name.clone().unwrap_or_default();
+    let mut set = |entry: &mut PasswordEntry, key: &str, value: &Option<String>| {
+        set_field_or_comment(entry, key, value.as_deref().unwrap_or_default(), skipped)
+    };
+
+    match item.item_type {
+        LOGIN => {
+            entry.entry_type = EntryType::ClassicPassword;
+            entry.comment = item.notes.clone().unwrap_or_default();
+            if let Some(login) = &item.login {
+                set(&mut entry, "username", &login.username);
+                set(&mut entry, "password", &login.password);
+                set(&mut entry, "totp", &login.totp);
+                let uris: Vec<&String> = login
+                    .uris
+                    .iter()
+                    .flatten()
+                    .filter_map(|x| x.uri.as_ref())
+                    .collect();
+                if let Some(uri) = uris.first() {
+                    entry.url = uri.to_string();
+                }
+                for uri in uris.iter().skip(1) {
+                    append_line(&mut entry.comment, &format!("url: {}", uri));
+                }
+            }
+        }
+        SECURE_NOTE => {
+            entry.entry_type = EntryType::SecureNote;
+            entry.comment = item.notes.clone().unwrap_or_default();
+        }
+        CARD => {
+            entry.entry_type = EntryType::PaymentCard;
+            entry.comment = item.notes.clone().unwrap_or_default();
+            if let Some(card) = &item.card {
+                set(&mut entry, "cardholder", &card.cardholder_name);
+                set(&mut entry, "number", &card.number);
+                set(&mut entry, "cvv", &card.code);
+                if let (Some(month), Some(year)) = (&card.exp_month, &card.exp_year) {
+                    set(
+                        &mut entry,
+                        "expiry",
+                        &Some(format!("{:0>2}/{}", month, year)),
+                    );
+                }
+                set(&mut entry, "brand", &card.brand);
+            }
+        }
+        IDENTITY => {
+            entry.entry_type = EntryType::Identity;
+            entry.comment = item.notes.clone().unwrap_or_default();
+            if let Some(identity) = &item.identity {
+                let full_name = [
+                    &identity.title,
+                    &identity.first_name,
+                    &identity.middle_name,
+                    &identity.last_name,
+                ]
+                .iter()
+                .filter_map(|x| x.as_deref())
+                .filter(|x| !x.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+                set(&mut entry, "full_name", &Some(full_name));
+                set(&mut entry, "email", &identity.email);
+                set(&mut entry, "phone", &identity.phone);
+                let address = [
+                    &identity.address1,
+                    &identity.address2,
+                    &identity.address3,
+                    &identity.postal_code,
+                    &identity.city,
+                    &identity.state,
+                    &identity.country,
+                ]
+                .iter()
+                .filter_map(|x| x.as_deref())
+                .filter(|x| !x.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+                set(&mut entry, "address", &Some(address));
+                set(&mut entry, "company", &identity.company);
+                set(&mut entry, "username", &identity.username);
+                set(&mut entry, "ssn", &identity.ssn);
+                set(&mut entry, "passport", &identity.passport_number);
+                set(&mut entry, "license", &identity.license_number);
+            }
+        }
+        SSH_KEY => {
+            entry.entry_type = EntryType::SshKey;
+            entry.comment = item.notes.clone().unwrap_or_default();
+            if let Some(ssh_key) = &item.ssh_key {
+                set(&mut entry, "private_key", &ssh_key.private_key);
+                set(&mut entry, "public_key", &ssh_key.public_key);
+            }
+        }
+        _ => return None,
+    }
+
+    for field in item.fields.iter() {
+        let name = field.name.clone().unwrap_or_default();
+        match field.field_type {
+            LINKED_FIELD => skipped.push(format!(
+                "linked field '{}' of '{}', it only refers to another field",
+                name, entry.title
+            )),
+            HIDDEN_FIELD => skip_secret(&entry, &name, skipped),
+            _ => {
+                let value = field.value.as_deref().unwrap_or_default();
+                append_line(&mut entry.comment, &format!("{}: {}", name, value));
+            }
+        }
+    }
+
+    if let Some(revision_date) = &item.revision_date {
+        if let std::result::Result::Ok(time) = chrono::DateTime::parse_from_rfc3339(revision_date) {
+            entry.last_modified = time.with_timezone(&chrono::Local).to_string();
+        }
+    }
+    Some(entry)
+}
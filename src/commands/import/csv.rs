@@ -0,0 +1,223 @@
+use anyhow::{anyhow, Ok};
+use std::fs;
+use std::path::Path;
+
+use super::{append_line, new_entry, ImportResult};
+use crate::database::fields::FieldSpec;
+use crate::database::structures::{EntryType, PasswordEntry};
+
+/// Column names of common exports (Chrome, Firefox, Bitwarden, KeePassXC, ...) per vault field
+const KNOWN_COLUMNS: [(&str, &[&str]); 7] = [
+    ("title", &["title", "name", "account", "entry"]),
+    (
+        "name",
+        &[
+            "username",
+            "user name",
+            "login",
+            "login_username",
+            "user",
+            "email",
+        ],
+    ),
+    ("value", &["password", "login_password", "pass", "secret"]),
+    (
+        "url",
+        &[
+            "url",
+            "website",
+            "web site",
+            "login_uri",
+            "uri",
+            "origin",
+            "hostname",
+        ],
+    ),
+    ("totp", &["totp", "otp", "login_totp", "one-time password"]),
+    (
+        "comment",
+        &["notes", "note", "comment", "comments", "extra"],
+    ),
+    ("group", &["group", "folder", "category", "path"]),
+];
+
+/// Where a mapped column ends up
+#[derive(Clone, Copy)]
+enum Target {
+    Field(&'static FieldSpec),
+    Group,
+}
+
+/// Reads the entries of a CSV file, `map` looks like `title=Name,value=Password` and accepts
+/// column names or 1-based column numbers. Without it the columns are detected from the header
+pub fn read_csv(file: &Path, map: Option<&str>) -> Result<ImportResult, anyhow::Error> {
+    let text = fs::read_to_string(file)?;
+    let mut reader = ::csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(detect_delimiter(&text))
+        .from_reader(text.as_bytes());
+    let mut rows = Vec::new();
+    for row in reader.records() {
+        rows.push(row?.iter().map(|x| x.to_owned()).collect::<Vec<String>>());
+    }
+    if rows.is_empty() {
+        return Err(anyhow!("{} is empty", file.display()));
+    }
+
+    let mapping = match map {
+        Some(map) => parse_map(map)?,
+        None => Vec::new(),
+    };
+    let header = if is_header(&rows[0], &mapping) {
+        Some(rows.remove(0))
+    } else {
+        None
+    };
+
+    let columns = match (&header, mapping.is_empty()) {
+        (Some(header), true) => detect_columns(header)?,
+        (None, true) => {
+            return Err(anyhow!(
+                "the first row is no header, please describe the columns with --map, e.g. --map title=1,name=2,value=3"
+            ))
+        }
+        (_, false) => resolve_columns(&mapping, header.as_deref())?,
+    };
+
+    let mut result = ImportResult {
+        entries: Vec::new(),
+        skipped: Vec::new(),
+    };
+    // the header is line 1 of the file
+    let first_line = if header.is_some() { 2 } else { 1 };
+    for (index, row) in rows.iter().enumerate() {
+        if row.iter().all(|x| x.trim().is_empty()) {
+            continue;
+        }
+        match convert_row(row, &columns, header.as_deref()) {
+            std::result::Result::Ok(entry) => result.entries.push(entry),
+            Err(err) => result
+                .skipped
+                .push(format!("line {}: {}", first_line + index, err)),
+        }
+    }
+    Ok(result)
+}
+
+/// Spreadsheets in many languages use semicolons or tabs instead of commas
+fn detect_delimiter(text: &str) -> u8 {
+    let first_line = text.lines().next().unwrap_or_default();
+    [b',', b';', b'\t']
+        .iter()
+        .copied()
+        .max_by_key(|x| first_line.matches(*x as char).count())
+        .unwrap_or(b',')
+}
+
+fn parse_map(map: &str) -> Result<Vec<(Target, String)>, anyhow::Error> {
+    map.split(',')
+        .map(|pair| {
+            let (target, column) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("'{}' in --map has to look like field=column", pair))?;
+            Ok((parse_target(target.trim())?, column.trim().to_owned()))
+        })
+        .collect()
+}
+
+fn parse_target(target: &str) -> Result<Target, anyhow::Error> {
+    match target {
+        "group" | "folder" => Ok(Target::Group),
+        _ => Ok(Target::Field(
+            EntryType::ClassicPassword.field_by_key(target)?,
+        )),
+    }
+}
+
+/// The first row is a header if it contains a mapped column name or a well known column name
+fn is_header(row: &[String], mapping: &[(Target, String)]) -> bool {
+    row.iter().any(|cell| {
+        let cell = cell.trim().to_lowercase();
+        mapping
+            .iter()
+            .any(|(_, column)| column.to_lowercase() == cell)
+            || KNOWN_COLUMNS
+                .iter()
+                .any(|(_, names)| names.contains(&cell.as_str()))
+    })
+}
+
+fn detect_columns(header: &[String]) -> Result<Vec<(Target, usize)>, anyhow::Error> {
+    let mut columns: Vec<(Target, usize)> = Vec::new();
+    for (target, names) in KNOWN_COLUMNS.iter() {
+        let position = header
+            .iter()
+            .position(|x| names.contains(&x.trim().to_lowercase().as_str()));
+        if let Some(position) = position {
+            columns.push((parse_target(target)?, position));
+        }
+    }
+    if columns.is_empty() {
+        return Err(anyhow!(
+            "no known columns found, please describe them with --map"
+        ));
+    }
+    Ok(columns)
+}
+
+fn resolve_columns(
+    mapping: &[(Target, String)],
+    header: Option<&[String]>,
+) -> Result<Vec<(Target, usize)>, anyhow::Error> {
+    mapping
+        .iter()
+        .map(|(target, column)| {
+            let target = *target;
+            if let std::result::Result::Ok(number) = column.parse::<usize>() {
+                if number == 0 {
+                    return Err(anyhow!("column numbers start at 1"));
+                }
+                return Ok((target, number - 1));
+            }
+            let position = header.and_then(|header| {
+                header
+                    .iter()
+                    .position(|x| x.trim().eq_ignore_ascii_case(column))
+            });
+            match position {
+                Some(position) => Ok((target, position)),
+                None => Err(anyhow!("column '{}' wasn't found in the header", column)),
+            }
+        })
+        .collect()
+}
+
+/// Columns of the header which aren't mapped are appended to the comment
+fn convert_row(
+    row: &[String],
+    columns: &[(Target, usize)],
+    header: Option<&[String]>,
+) -> Result<PasswordEntry, anyhow::Error> {
+    let mut entry = new_entry();
+    for (target, position) in columns.iter() {
+        let value = row.get(*position).cloned().unwrap_or_default();
+        match target {
+            Target::Field(spec) => entry.set_field(spec, value)?,
+            Target::Group => entry.group = value.trim_matches('/').to_owned(),
+        }
+    }
+    if let Some(header) = header {
+        for (position, value) in row.iter().enumerate() {
+            let mapped = columns.iter().any(|(_, x)| *x == position);
+            if !mapped && !value.is_empty() {
+                let name = header.get(position).map_or("", |x| x.as_str());
+                append_line(&mut entry.comment, &format!("{}: {}", name, value));
+            }
+        }
+    }
+    if entry.title.is_empty() && entry.value.is_empty() {
+        return Err(anyhow!("neither a title nor a value"));
+    }
+    Ok(entry)
+}
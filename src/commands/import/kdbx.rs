@@ -0,0 +1,162 @@
+use anyhow::{anyhow, Ok};
+use base64::Engine;
+use chrono::{Local, NaiveDateTime, TimeZone};
+use keepass::db::{EntryRef, GroupRef};
+use keepass::{Database, DatabaseKey};
+use std::fs::File;
+use std::path::Path;
+
+use super::{append_line, new_entry, set_field_or_comment, skip_secret, ImportResult};
+use crate::database::structures::{CustomFile, PasswordEntry};
+
+/// Strings every KeePass entry has, everything else is a custom string
+const STANDARD_FIELDS: [&str; 5] = ["Title", "UserName", "Password", "URL", "Notes"];
+
+/// Reads all entries of a KDBX 3.1 or 4 database, the cipher and KDF are taken from its header
+pub fn read_kdbx(file: &Path, keyfile: Option<&Path>) -> Result<ImportResult, anyhow::Error> {
+    let password = rpassword::prompt_password(
+        "Please enter the password of the KeePass database (empty if it only uses a key file):",
+    )?;
+    let mut key = DatabaseKey::new();
+    if !password.is_empty() {
+        key = key.with_password(&password);
+    }
+    if let Some(keyfile) = keyfile {
+        key = key.with_keyfile(&mut File::open(keyfile)?)?;
+    }
+    if key.is_empty() {
+        return Err(anyhow!("a password or a key file is required"));
+    }
+
+    let keepass = Database::open(&mut File::open(file)?, key)
+        .map_err(|err| anyhow!("could not open {}: {}", file.display(), err))?;
+
+    let mut result = ImportResult {
+        entries: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let recycle_bin = keepass.recycle_bin().map(|x| x.id());
+    let mut recycled = 0;
+    let mut history = 0;
+    // the root group is the database itself, so its name isn't part of the group paths
+    let mut groups = vec![(keepass.root().id(), String::new())];
+    while let Some((id, path)) = groups.pop() {
+        let group = keepass.group(id).expect("child groups exist");
+        if Some(id) == recycle_bin {
+            recycled += count_entries(&group);
+            continue;
+        }
+        for entry in group.entries() {
+            history += entry.history.as_ref().map_or(0, |x| x.get_entries().len());
+            result
+                .entries
+                .push(convert_entry(&entry, &path, &mut result.skipped));
+        }
+        for child in group.groups() {
+            groups.push((child.id(), group_path(&path, &child.name)));
+        }
+    }
+
+    if recycled > 0 {
+        result
+            .skipped
+            .push(format!("{} entries in the KeePass recycle bin", recycled));
+    }
+    if history > 0 {
+        result
+            .skipped
+            .push(format!("{} older versions from the entry history", history));
+    }
+    Ok(result)
+}
+
+fn count_entries(group: &GroupRef) -> usize {
+    group.entries().count() + group.groups().map(|x| count_entries(&x)).sum::<usize>()
+}
+
+fn convert_entry(entry: &EntryRef, group: &str, skipped: &mut Vec<String>) -> PasswordEntry {
+    let strings = entry
+        .fields
+        .iter()
+        .map(|(key, value)| (key.clone(), value.get().clone(), value.is_protected()))
+        .collect();
+    let attachments = entry
+        .attachments_named()
+        .map(|(name, attachment)| (name.to_owned(), attachment.get().clone()))
+        .collect();
+    keepass_entry(
+        strings,
+        &entry.tags,
+        attachments,
+        entry.times.last_modification,
+        group,
+        skipped,
+    )
+}
+
+pub(super) fn group_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+/// Builds an entry from the strings of a KeePass entry and whether they are protected, used for KDBX
+/// and XML imports. Custom strings and tags are appended to the comment so they stay visible in the
+/// vault, protected custom strings are left out instead
+pub(super) fn keepass_entry(
+    mut strings: Vec<(String, String, bool)>,
+    tags: &[String],
+    attachments: Vec<(String, Vec<u8>)>,
+    last_modification: Option<NaiveDateTime>,
+    group: &str,
+    skipped: &mut Vec<String>,
+) -> PasswordEntry {
+    let mut entry = new_entry();
+    strings.sort_by(|a, b| a.0.cmp(&b.0));
+    let get = |key: &str| {
+        strings
+            .iter()
+            .find(|x| x.0 == key)
+            .map(|x| x.1.clone())
+            .unwrap_or_default()
+    };
+    entry.title = get("Title");
+    entry.name = get("UserName");
+    entry.value = get("Password");
+    entry.url = get("URL");
+    entry.comment = get("Notes");
+    for (key, value, protected) in strings.iter() {
+        match key.as_str() {
+            // KeePassXC stores the TOTP as otpauth URI, KeePass 2 only the secret
+            "otp" | "TimeOtp-Secret-Base32" => {
+                set_field_or_comment(&mut entry, "totp", value, skipped)
+            }
+            key if STANDARD_FIELDS.contains(&key) => {}
+            key if *protected => skip_secret(&entry, key, skipped),
+            key => append_line(&mut entry.comment, &format!("{}: {}", key, value)),
+        }
+    }
+    if !tags.is_empty() {
+        append_line(&mut entry.comment, &format!("Tags: {}", tags.join(", ")));
+    }
+
+    for (name, data) in attachments {
+        if data.is_empty() {
+            skipped.push(format!("empty attachment '{}' of '{}'", name, entry.title));
+            continue;
+        }
+        entry.files.push(CustomFile {
+            content: base64::engine::general_purpose::STANDARD.encode(&data),
+            comment: String::new(),
+            name,
+        });
+    }
+
+    if let Some(time) = last_modification {
+        entry.last_modified = Local.from_utc_datetime(&time).to_string();
+    }
+    entry.group = group.to_owned();
+    entry
+}
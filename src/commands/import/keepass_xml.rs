@@ -0,0 +1,184 @@
+use anyhow::{anyhow, Ok};
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeDelta};
+use flate2::read::GzDecoder;
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use super::kdbx::{group_path, keepass_entry};
+use super::ImportResult;
+
+/// Reads the unencrypted XML export of KeePass 2 (File > Export > KeePass XML)
+pub fn read_keepass_xml(file: &Path) -> Result<ImportResult, anyhow::Error> {
+    let text = fs::read_to_string(file)?;
+    let document = Document::parse(&text)
+        .map_err(|err| anyhow!("{} is not a valid XML file: {}", file.display(), err))?;
+    let keepass_file = document.root_element();
+    if keepass_file.tag_name().name() != "KeePassFile" {
+        return Err(anyhow!("{} is not a KeePass XML export", file.display()));
+    }
+
+    let meta = child(keepass_file, "Meta");
+    let recycle_bin = meta
+        .and_then(|x| child_text(x, "RecycleBinUUID"))
+        .unwrap_or_default();
+    let binaries = match meta.and_then(|x| child(x, "Binaries")) {
+        Some(binaries) => read_binaries(binaries)?,
+        None => HashMap::new(),
+    };
+    let root_group = child(keepass_file, "Root")
+        .and_then(|x| child(x, "Group"))
+        .ok_or_else(|| anyhow!("{} doesn't contain any groups", file.display()))?;
+
+    let mut result = ImportResult {
+        entries: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let mut recycled = 0;
+    let mut history = 0;
+    // the root group is the database itself, so its name isn't part of the group paths
+    let mut groups = vec![(root_group, String::new())];
+    while let Some((group, path)) = groups.pop() {
+        if !recycle_bin.is_empty() && child_text(group, "UUID").unwrap_or_default() == recycle_bin {
+            recycled += group
+                .descendants()
+                .filter(|x| x.has_tag_name("Entry"))
+                .filter(|x| !x.ancestors().any(|x| x.has_tag_name("History")))
+                .count();
+            continue;
+        }
+        for node in group.children().filter(|x| x.is_element()) {
+            match node.tag_name().name() {
+                "Entry" => {
+                    history += child(node, "History").map_or(0, |x| {
+                        x.children().filter(|x| x.has_tag_name("Entry")).count()
+                    });
+                    result
+                        .entries
+                        .push(convert_entry(node, &path, &binaries, &mut result.skipped));
+                }
+                "Group" => {
+                    let name = child_text(node, "Name").unwrap_or_default();
+                    groups.push((node, group_path(&path, &name)));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if recycled > 0 {
+        result
+            .skipped
+            .push(format!("{} entries in the KeePass recycle bin", recycled));
+    }
+    if history > 0 {
+        result
+            .skipped
+            .push(format!("{} older versions from the entry history", history));
+    }
+    Ok(result)
+}
+
+fn convert_entry(
+    entry: Node,
+    group: &str,
+    binaries: &HashMap<String, Vec<u8>>,
+    skipped: &mut Vec<String>,
+) -> crate::database::structures::PasswordEntry {
+    let mut strings = Vec::new();
+    let mut attachments = Vec::new();
+    for node in entry.children().filter(|x| x.is_element()) {
+        let key = child_text(node, "Key").unwrap_or_default();
+        let value = child(node, "Value");
+        match node.tag_name().name() {
+            "String" => strings.push((
+                key,
+                value.and_then(|x| x.text()).unwrap_or_default().to_owned(),
+                value.is_some_and(is_protected),
+            )),
+            "Binary" => match value.and_then(|x| x.attribute("Ref")) {
+                Some(reference) => match binaries.get(reference) {
+                    Some(data) => attachments.push((key, data.clone())),
+                    None => {
+                        skipped.push(format!("attachment '{}' references a missing binary", key))
+                    }
+                },
+                None => match decode_base64(value.and_then(|x| x.text()).unwrap_or_default()) {
+                    std::result::Result::Ok(data) => attachments.push((key, data)),
+                    Err(_) => skipped.push(format!("attachment '{}' is not valid base64", key)),
+                },
+            },
+            _ => {}
+        }
+    }
+
+    let tags: Vec<String> = child_text(entry, "Tags")
+        .unwrap_or_default()
+        .split([';', ','])
+        .map(|x| x.trim().to_owned())
+        .filter(|x| !x.is_empty())
+        .collect();
+    let last_modification = child(entry, "Times")
+        .and_then(|x| child_text(x, "LastModificationTime"))
+        .and_then(|x| parse_time(&x));
+
+    keepass_entry(
+        strings,
+        &tags,
+        attachments,
+        last_modification,
+        group,
+        skipped,
+    )
+}
+
+/// Exports mark protected strings with ProtectInMemory, XML taken from KDBX files with Protected
+fn is_protected(value: Node) -> bool {
+    ["ProtectInMemory", "Protected"]
+        .iter()
+        .any(|x| value.attribute(*x) == Some("True"))
+}
+
+/// Binaries in the Meta section, entries reference them by their ID
+fn read_binaries(binaries: Node) -> Result<HashMap<String, Vec<u8>>, anyhow::Error> {
+    let mut result = HashMap::new();
+    for binary in binaries.children().filter(|x| x.has_tag_name("Binary")) {
+        let id = binary.attribute("ID").unwrap_or_default().to_owned();
+        let mut data = decode_base64(binary.text().unwrap_or_default())?;
+        if binary.attribute("Compressed") == Some("True") {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(data.as_slice()).read_to_end(&mut decompressed)?;
+            data = decompressed;
+        }
+        result.insert(id, data);
+    }
+    Ok(result)
+}
+
+/// Exports use ISO 8601, XML taken from KDBX 4 files base64 encoded seconds since 0001-01-01
+fn parse_time(text: &str) -> Option<NaiveDateTime> {
+    if let std::result::Result::Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time.naive_utc());
+    }
+    let bytes = decode_base64(text).ok()?;
+    let seconds = i64::from_le_bytes(bytes.get(..8)?.try_into().ok()?);
+    NaiveDate::from_ymd_opt(1, 1, 1)?
+        .and_hms_opt(0, 0, 0)?
+        .checked_add_signed(TimeDelta::try_seconds(seconds)?)
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, anyhow::Error> {
+    Ok(base64::engine::general_purpose::STANDARD.decode(text.trim())?)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|x| x.has_tag_name(name))
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name).map(|x| x.text().unwrap_or_default().to_owned())
+}
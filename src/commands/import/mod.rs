@@ -0,0 +1,208 @@
+use anyhow::{anyhow, Ok};
+use chrono::Local;
+use clap::ValueEnum;
+use uuid::Uuid;
+
+use crate::database::lock::VaultLock;
+use crate::database::operations::{resolve_database_path, Database};
+use crate::database::structures::{DatabaseFile, EntryType, PasswordEntry};
+use crate::Import;
+
+mod bitwarden;
+mod csv;
+mod kdbx;
+mod keepass_xml;
+mod one_password;
+mod pass;
+
+/// Formats `vault import --from` understands
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ImportFormat {
+    /// KeePass database (KDBX 3.1 and 4)
+    Kdbx,
+    /// Unencrypted XML export of KeePass 2
+    KeepassXml,
+    /// Any CSV file, columns are mapped with --map
+    Csv,
+    /// Unencrypted JSON export of Bitwarden
+    Bitwarden,
+    /// 1Password export (1PUX)
+    #[value(name = "1pux")]
+    OnePux,
+    /// Directory of a password store (pass) with already decrypted files
+    Pass,
+}
+
+/// Entries read from another password manager and everything that couldn't be imported
+pub struct ImportResult {
+    pub entries: Vec<PasswordEntry>,
+    pub skipped: Vec<String>,
+}
+
+/// Imports the entries of another password manager into an existing vault
+pub fn import_entries(mut args: Import) -> Result<(), anyhow::Error> {
+    if args.map.is_some() && args.from != ImportFormat::Csv {
+        return Err(anyhow!("--map can only be used with --from csv"));
+    }
+    let path = resolve_database_path(&args.path, &args.database_name)?;
+    // a dry run only reads the vault
+    let _lock = if args.dry_run {
+        None
+    } else {
+        Some(VaultLock::acquire_for_writing(&path)?)
+    };
+    args.path = Some(path);
+    let mut db: DatabaseFile = *Database::new(&mut args.path, args.database_name.as_str())?;
+
+    let result = match args.from {
+        ImportFormat::Kdbx => kdbx::read_kdbx(&args.file, args.keyfile.as_deref())?,
+        ImportFormat::KeepassXml => keepass_xml::read_keepass_xml(&args.file)?,
+        ImportFormat::Csv => csv::read_csv(&args.file, args.map.as_deref())?,
+        ImportFormat::Bitwarden => bitwarden::read_bitwarden(&args.file)?,
+        ImportFormat::OnePux => one_password::read_1pux(&args.file)?,
+        ImportFormat::Pass => pass::read_password_store(&args.file)?,
+    };
+    let result = remove_duplicates(&db, result);
+
+    let imported = result.entries.len();
+    if args.dry_run {
+        for entry in result.entries.iter() {
+            println!("{}", describe_entry(entry));
+        }
+        println!(
+            "Would import {} entries from {}, nothing was saved (dry run)",
+            imported,
+            args.file.display()
+        );
+    } else {
+        db.entries.extend(result.entries);
+        db.save_database(&args.path)?;
+        println!("Imported {} entries from {}", imported, args.file.display());
+    }
+    if !result.skipped.is_empty() {
+        println!("Skipped {}:", result.skipped.len());
+        for reason in result.skipped.iter() {
+            println!("  {}", reason);
+        }
+    }
+    Ok(())
+}
+
+/// One line per entry for the dry run, secrets are never printed
+fn describe_entry(entry: &PasswordEntry) -> String {
+    let title = if entry.group.is_empty() {
+        entry.title.clone()
+    } else {
+        format!("{}/{}", entry.group, entry.title)
+    };
+    let mut description = format!("+ {} [{}]", title, entry.entry_type.key());
+    if !entry.name.is_empty() {
+        description.push_str(&format!(" name: {}", entry.name));
+    }
+    if !entry.url.is_empty() {
+        description.push_str(&format!(" url: {}", entry.url));
+    }
+    if !entry.value.is_empty() {
+        description.push_str(" (with value)");
+    }
+    if !entry.files.is_empty() {
+        description.push_str(&format!(" ({} files)", entry.files.len()));
+    }
+    description
+}
+
+/// Entries which already exist in the vault or earlier in the same import are skipped
+fn remove_duplicates(db: &DatabaseFile, result: ImportResult) -> ImportResult {
+    let mut entries: Vec<PasswordEntry> = Vec::new();
+    let mut skipped = result.skipped;
+    for entry in result.entries {
+        if db
+            .entries
+            .iter()
+            .chain(entries.iter())
+            .any(|x| is_duplicate(x, &entry))
+        {
+            skipped.push(format!("'{}' already exists in the vault", entry.title));
+        } else {
+            entries.push(entry);
+        }
+    }
+    ImportResult { entries, skipped }
+}
+
+fn is_duplicate(a: &PasswordEntry, b: &PasswordEntry) -> bool {
+    a.entry_type == b.entry_type
+        && a.title == b.title
+        && a.name == b.name
+        && a.value == b.value
+        && a.url == b.url
+        // notes only consist of the title and the comment
+        && (!a.value.is_empty() || a.comment == b.comment)
+}
+
+/// Stores the value in the field of the entry type. Values the field doesn't accept are kept in the comment,
+/// unless they are secret because the comment is shown unmasked, those are only reported by their field
+fn set_field_or_comment(
+    entry: &mut PasswordEntry,
+    key: &str,
+    value: &str,
+    skipped: &mut Vec<String>,
+) {
+    if value.is_empty() {
+        return;
+    }
+    let spec = match entry.entry_type.field_by_key(key) {
+        std::result::Result::Ok(spec) => spec,
+        Err(_) => {
+            skip_secret(entry, key, skipped);
+            return;
+        }
+    };
+    if let Err(err) = entry.set_field(spec, value.to_owned()) {
+        if spec.secret {
+            skipped.push(format!(
+                "'{}': {}, the value was not imported",
+                entry.title, err
+            ));
+        } else {
+            skipped.push(format!(
+                "'{}': {}, the value was kept in the comment",
+                entry.title, err
+            ));
+            append_line(&mut entry.comment, &format!("{}: {}", spec.label, value));
+        }
+    }
+}
+
+/// Hidden values without a secret field to store them in are left out instead of ending up in the comment
+fn skip_secret(entry: &PasswordEntry, name: &str, skipped: &mut Vec<String>) {
+    skipped.push(format!(
+        "'{}': the hidden field '{}' was not imported, the entry type has no secret field for it",
+        entry.title, name
+    ));
+}
+
+/// Password entry every importer starts from
+fn new_entry() -> PasswordEntry {
+    PasswordEntry {
+        id: Uuid::new_v4().to_string(),
+        title: String::new(),
+        name: String::new(),
+        value: String::new(),
+        url: String::new(),
+        comment: String::new(),
+        entry_type: EntryType::ClassicPassword,
+        last_modified: Local::now().to_string(),
+        files: Vec::new(),
+        extra_fields: Vec::new(),
+        group: String::new(),
+    }
+}
+
+/// Fields the vault has no place for are appended to the comment as `key: value` lines
+fn append_line(text: &mut String, line: &str) {
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(line);
+}
@@ -0,0 +1,240 @@
+use anyhow::{anyhow, Ok};
+use base64::Engine;
+use chrono::{DateTime, Local, TimeZone};
+use serde_json::Value;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+use super::{append_line, new_entry, set_field_or_comment, skip_secret, ImportResult};
+use crate::database::structures::{CustomFile, EntryType, PasswordEntry};
+
+/// Reads a 1Password export (File > Export > 1PUX), a zip file with the items in export.data
+pub fn read_1pux(file: &Path) -> Result<ImportResult, anyhow::Error> {
+    let mut archive = ZipArchive::new(File::open(file)?)
+        .map_err(|err| anyhow!("{} is not a 1PUX file: {}", file.display(), err))?;
+    let mut data = String::new();
+    archive
+        .by_name("export.data")
+        .map_err(|_| anyhow!("{} doesn't contain export.data", file.display()))?
+        .read_to_string(&mut data)?;
+    let export: Value = serde_json::from_str(&data)?;
+
+    let mut result = ImportResult {
+        entries: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let mut history = 0;
+    for vault in array(&export["accounts"]).flat_map(|x| array(&x["vaults"])) {
+        let vault_name = text(&vault["attrs"]["name"]);
+        for item in array(&vault["items"]) {
+            let title = text(&item["overview"]["title"]);
+            let state = text(&item["state"]);
+            if !state.is_empty() && state != "active" {
+                result.skipped.push(format!("'{}' is {}", title, state));
+                continue;
+            }
+            history += array(&item["details"]["passwordHistory"]).count();
+            let mut entry = convert_item(item, &mut archive, &mut result.skipped);
+            entry.group = vault_name.clone();
+            result.entries.push(entry);
+        }
+    }
+    if history > 0 {
+        result.skipped.push(format!(
+            "{} older passwords from the password history",
+            history
+        ));
+    }
+    Ok(result)
+}
+
+fn convert_item(
+    item: &Value,
+    archive: &mut ZipArchive<File>,
+    skipped: &mut Vec<String>,
+) -> PasswordEntry {
+    let overview = &item["overview"];
+    let details = &item["details"];
+    let mut entry = new_entry();
+    entry.title = text(&overview["title"]);
+    entry.entry_type = match text(&item["categoryUuid"]).as_str() {
+        "002" => EntryType::PaymentCard,
+        "003" => EntryType::SecureNote,
+        "004" => EntryType::Identity,
+        "114" => EntryType::SshKey,
+        // logins, passwords and everything without its own type in the vault
+        _ => EntryType::ClassicPassword,
+    };
+    entry.comment = text(&details["notesPlain"]);
+
+    let mut urls = vec![text(&overview["url"])];
+    urls.extend(array(&overview["urls"]).map(|x| text(&x["url"])));
+    urls.retain(|x| !x.is_empty());
+    urls.dedup();
+    if entry.entry_type == EntryType::ClassicPassword || entry.entry_type == EntryType::Identity {
+        if let Some(url) = urls.first() {
+            entry.url = url.clone();
+        }
+        for url in urls.iter().skip(1) {
+            append_line(&mut entry.comment, &format!("url: {}", url));
+        }
+    }
+
+    for field in array(&details["loginFields"]) {
+        let value = text(&field["value"]);
+        match text(&field["designation"]).as_str() {
+            "username" => set_field_or_comment(&mut entry, "username", &value, skipped),
+            "password" => set_field_or_comment(&mut entry, "password", &value, skipped),
+            // P is the type of concealed login fields
+            _ if !value.is_empty() && text(&field["type"]) == "P" => {
+                skip_secret(&entry, &text(&field["name"]), skipped)
+            }
+            _ if !value.is_empty() => append_line(
+                &mut entry.comment,
+                &format!("{}: {}", text(&field["name"]), value),
+            ),
+            _ => {}
+        }
+    }
+    // items of the password category only have this single field
+    set_field_or_comment(&mut entry, "password", &text(&details["password"]), skipped);
+
+    let mut full_name = Vec::new();
+    for section in array(&details["sections"]) {
+        for field in array(&section["fields"]) {
+            let id = text(&field["id"]);
+            let label = text(&field["title"]);
+            let (kind, value) = match field["value"].as_object().and_then(|x| x.iter().next()) {
+                Some((kind, value)) => (kind.as_str(), value),
+                None => continue,
+            };
+            if kind == "file" {
+                attach_file(&mut entry, archive, value, skipped);
+                continue;
+            }
+            let value = field_value(kind, value);
+            if value.is_empty() {
+                continue;
+            }
+            let key = match (&entry.entry_type, kind, id.as_str()) {
+                (_, "totp", _) => "totp",
+                (_, "sshKey", _) => "private_key",
+                (EntryType::PaymentCard, _, "cardholder") => "cardholder",
+                (EntryType::PaymentCard, _, "ccnum") => "number",
+                (EntryType::PaymentCard, _, "cvv") => "cvv",
+                (EntryType::PaymentCard, _, "expiry") => "expiry",
+                (EntryType::Identity, _, "firstname" | "initial" | "lastname") => {
+                    full_name.push(value);
+                    continue;
+                }
+                (EntryType::Identity, _, "email") => "email",
+                (EntryType::Identity, _, "defphone") => "phone",
+                (EntryType::Identity, _, "address") => "address",
+                (EntryType::Identity, _, "birthdate") => "birthday",
+                (EntryType::ClassicPassword, _, "username") => "username",
+                (EntryType::ClassicPassword, _, "password") => "password",
+                (_, "concealed", _) => {
+                    skip_secret(&entry, &label, skipped);
+                    continue;
+                }
+                _ => {
+                    append_line(&mut entry.comment, &format!("{}: {}", label, value));
+                    continue;
+                }
+            };
+            set_field_or_comment(&mut entry, key, &value, skipped);
+        }
+    }
+    set_field_or_comment(&mut entry, "full_name", &full_name.join(" "), skipped);
+
+    let document = &details["documentAttributes"];
+    if document.is_object() {
+        attach_file(&mut entry, archive, document, skipped);
+    }
+
+    let tags: Vec<String> = array(&overview["tags"]).map(text).collect();
+    if !tags.is_empty() {
+        append_line(&mut entry.comment, &format!("Tags: {}", tags.join(", ")));
+    }
+    if let Some(updated_at) = item["updatedAt"].as_i64() {
+        if let Some(time) = Local.timestamp_opt(updated_at, 0).single() {
+            entry.last_modified = time.to_string();
+        }
+    }
+    entry
+}
+
+/// Files are stored as `files/<documentId>__<fileName>` inside the export
+fn attach_file(
+    entry: &mut PasswordEntry,
+    archive: &mut ZipArchive<File>,
+    attributes: &Value,
+    skipped: &mut Vec<String>,
+) {
+    let name = text(&attributes["fileName"]);
+    let prefix = format!("files/{}", text(&attributes["documentId"]));
+    let path = archive
+        .file_names()
+        .find(|x| x.starts_with(&prefix))
+        .map(|x| x.to_owned());
+    let mut data = Vec::new();
+    let read = match path {
+        Some(path) => archive
+            .by_name(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|mut x| Ok(x.read_to_end(&mut data)?)),
+        None => Err(anyhow!("missing in the export")),
+    };
+    match read {
+        std::result::Result::Ok(_) => entry.files.push(CustomFile {
+            content: base64::engine::general_purpose::STANDARD.encode(&data),
+            comment: String::new(),
+            name,
+        }),
+        Err(err) => skipped.push(format!("file '{}' of '{}': {}", name, entry.title, err)),
+    }
+}
+
+/// Converts the typed field values of 1Password to text
+fn field_value(kind: &str, value: &Value) -> String {
+    match kind {
+        // unix timestamp
+        "date" => value
+            .as_i64()
+            .and_then(|x| DateTime::from_timestamp(x, 0))
+            .map(|x| x.format("%Y-%m-%d").to_string())
+            .unwrap_or_default(),
+        // YYYYMM
+        "monthYear" => value
+            .as_i64()
+            .map(|x| format!("{:02}/{}", x % 100, x / 100))
+            .unwrap_or_default(),
+        "email" => match value.as_object() {
+            Some(email) => text(&email["email_address"]),
+            None => text(value),
+        },
+        "address" => ["street", "zip", "city", "state", "country"]
+            .iter()
+            .map(|x| text(&value[*x]))
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "sshKey" => text(&value["privateKey"]),
+        _ => text(value),
+    }
+}
+
+fn array(value: &Value) -> impl Iterator<Item = &Value> {
+    value.as_array().into_iter().flatten()
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Number(number) => number.to_string(),
+        Value::Bool(boolean) => boolean.to_string(),
+        _ => String::new(),
+    }
+}
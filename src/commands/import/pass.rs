@@ -0,0 +1,115 @@
+use anyhow::{anyhow, Ok};
+use std::fs;
+use std::path::Path;
+
+use super::{append_line, new_entry, set_field_or_comment, ImportResult};
+
+/// Extensions of the files in a password store, the decrypted copies may keep them
+const EXTENSIONS: [&str; 3] = ["gpg", "asc", "txt"];
+
+/// Reads a copy of a password store with decrypted files, e.g. created with
+/// `for f in $(find . -name '*.gpg'); do pass show "${f%.gpg}" > "$copy/${f%.gpg}"; done`
+pub fn read_password_store(directory: &Path) -> Result<ImportResult, anyhow::Error> {
+    if !directory.is_dir() {
+        return Err(anyhow!("{} is not a directory", directory.display()));
+    }
+    let mut result = ImportResult {
+        entries: Vec::new(),
+        skipped: Vec::new(),
+    };
+    read_directory(directory, "", &mut result)?;
+    Ok(result)
+}
+
+fn read_directory(
+    directory: &Path,
+    group: &str,
+    result: &mut ImportResult,
+) -> Result<(), anyhow::Error> {
+    let mut paths: Vec<_> = fs::read_dir(directory)?
+        .map(|x| x.map(|x| x.path()))
+        .collect::<Result<_, _>>()?;
+    paths.sort();
+    for path in paths {
+        let file_name = path
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // .git, .gpg-id and other files of pass itself
+        if file_name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            let child_group = if group.is_empty() {
+                file_name
+            } else {
+                format!("{}/{}", group, file_name)
+            };
+            read_directory(&path, &child_group, result)?;
+            continue;
+        }
+
+        let title = match path.extension().and_then(|x| x.to_str()) {
+            Some(extension) if EXTENSIONS.contains(&extension) => path
+                .file_stem()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            _ => file_name,
+        };
+        let name = if group.is_empty() {
+            title.clone()
+        } else {
+            format!("{}/{}", group, title)
+        };
+        let text = match fs::read(&path).map(String::from_utf8) {
+            std::result::Result::Ok(std::result::Result::Ok(text)) => text,
+            _ => {
+                result.skipped.push(format!(
+                    "'{}' is no text file, it may still be encrypted",
+                    name
+                ));
+                continue;
+            }
+        };
+        if text.starts_with("-----BEGIN PGP MESSAGE-----") {
+            result.skipped.push(format!(
+                "'{}' is still encrypted, decrypt it with pass show",
+                name
+            ));
+            continue;
+        }
+
+        let mut entry = new_entry();
+        entry.title = title;
+        entry.group = group.to_owned();
+        let mut lines = text.lines();
+        entry.value = lines.next().unwrap_or_default().to_owned();
+        // the lines after the password are free text, common keys are used for the fields
+        for line in lines {
+            if line.starts_with("otpauth://") {
+                set_field_or_comment(&mut entry, "totp", line.trim(), &mut result.skipped);
+                continue;
+            }
+            let field = line
+                .split_once(':')
+                .map(|(key, value)| (key.trim().to_lowercase(), value.trim()));
+            match field {
+                Some((key, value))
+                    if entry.name.is_empty()
+                        && ["login", "username", "user", "email"].contains(&key.as_str()) =>
+                {
+                    entry.name = value.to_owned()
+                }
+                Some((key, value))
+                    if entry.url.is_empty()
+                        && ["url", "website", "site"].contains(&key.as_str()) =>
+                {
+                    entry.url = value.to_owned()
+                }
+                _ => append_line(&mut entry.comment, line),
+            }
+        }
+        result.entries.push(entry);
+    }
+    Ok(())
+}
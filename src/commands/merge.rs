@@ -0,0 +1,112 @@
+use anyhow::{anyhow, Ok};
+use std::path::Path;
+
+use crate::database::fields::FieldSpec;
+use crate::database::lock::VaultLock;
+use crate::database::merge::{merge_databases, newest_side, Conflict, EntryState, Side};
+use crate::database::operations::Database;
+use crate::database::structures::DatabaseFile;
+use crate::utils::terminal_interactions::prompt_user;
+use crate::Merge;
+
+/// Merges two copies of a vault, by default the result replaces the first one
+pub fn merge_vaults(args: Merge) -> Result<(), anyhow::Error> {
+    let output = args.output.clone().unwrap_or_else(|| args.a.clone());
+    let _lock = VaultLock::acquire_for_writing(&output)?;
+    let password = rpassword::prompt_password(format!(
+        "Please enter the password of {}:",
+        args.a.display()
+    ))?;
+    let a: DatabaseFile = *DatabaseFile::load(&args.a, &password)?;
+    let b = load_with_password(&args.b, &password)?;
+    let base = match &args.base {
+        Some(base) => Some(load_with_password(base, &password)?),
+        None => None,
+    };
+
+    let (merged, report) = merge_databases(&a, &b, base.as_ref(), |conflict| {
+        if args.interactive {
+            ask_side(conflict)
+        } else {
+            Ok(newest_side(conflict))
+        }
+    })?;
+
+    merged.save_database(&Some(output.clone()))?;
+    println!(
+        "Merged into {}: {} changes from {}, {} changes from {}, {} conflicts, {} unchanged",
+        output.display(),
+        report.from_a,
+        args.a.display(),
+        report.from_b,
+        args.b.display(),
+        report.conflicts,
+        report.unchanged
+    );
+    Ok(())
+}
+
+/// Copies of a vault usually share the password, it is only asked for again if it doesn't fit
+pub fn load_with_password(path: &Path, password: &str) -> Result<DatabaseFile, anyhow::Error> {
+    if let std::result::Result::Ok(db) = DatabaseFile::load(path, password) {
+        return Ok(*db);
+    }
+    let password =
+        rpassword::prompt_password(format!("Please enter the password of {}:", path.display()))?;
+    Ok(*DatabaseFile::load(path, &password)?)
+}
+
+fn ask_side(conflict: &Conflict) -> Result<Side, anyhow::Error> {
+    let title = match (conflict.a, conflict.b) {
+        (Some(a), _) if !a.title().is_empty() => a.title().to_owned(),
+        (_, Some(b)) => b.title().to_owned(),
+        _ => String::new(),
+    };
+    println!("\nConflict in '{}' ({})", title, conflict.id);
+    println!("  a: {}", describe(conflict.a));
+    println!("  b: {}", describe(conflict.b));
+    if let (Some(EntryState::Active(a)), Some(EntryState::Active(b))) = (conflict.a, conflict.b) {
+        for spec in a.fields() {
+            let (value_a, value_b) = (a.get_field(spec), b.get_field(spec));
+            if value_a != value_b {
+                println!(
+                    "  {}: a = {}, b = {}",
+                    spec.label,
+                    show(spec, &value_a),
+                    show(spec, &value_b)
+                );
+            }
+        }
+    }
+    loop {
+        match prompt_user("Keep a or b? [a/b] ")?.trim() {
+            "a" => return Ok(Side::A),
+            "b" => return Ok(Side::B),
+            "" => return Err(anyhow!("merge aborted")),
+            _ => {}
+        }
+    }
+}
+
+fn describe(state: Option<EntryState>) -> String {
+    let time = state
+        .and_then(|x| x.changed_at())
+        .map(|x| x.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default();
+    match state {
+        Some(EntryState::Active(entry)) => {
+            format!("changed at {} ({})", time, entry.entry_type.label())
+        }
+        Some(EntryState::Recycled(_)) => format!("moved to the recycle bin at {}", time),
+        Some(EntryState::Purged(_)) => format!("deleted at {}", time),
+        None => String::from("missing"),
+    }
+}
+
+fn show(spec: &FieldSpec, value: &str) -> String {
+    if spec.secret {
+        String::from("(hidden)")
+    } else {
+        format!("'{}'", value)
+    }
+}
@@ -0,0 +1,11 @@
+#[cfg(unix)]
+pub mod agent;
+pub mod entries;
+pub mod export;
+pub mod git_credential;
+pub mod import;
+pub mod merge;
+#[cfg(unix)]
+pub mod ssh_agent;
+pub mod sync;
+pub mod vaults;
@@ -0,0 +1,457 @@
+use anyhow::{anyhow, Ok};
+use rsa::signature::{SignatureEncoding, Signer};
+use ssh_encoding::Encode;
+use ssh_key::private::{KeypairData, RsaKeypair};
+use ssh_key::sha2::{Sha256, Sha512};
+use ssh_key::{Algorithm, HashAlg, Mpint, PrivateKey, Signature};
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::database::operations::Database;
+use crate::database::structures::{DatabaseFile, EntryType};
+use crate::utils::terminal_interactions::prompt_user;
+use crate::SshAgent;
+
+// Message numbers from draft-miller-ssh-agent
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+const SSH_AGENT_RSA_SHA2_256: u32 = 2;
+const SSH_AGENT_RSA_SHA2_512: u32 = 4;
+
+/// Messages bigger than this are rejected, OpenSSH uses the same limit
+const MAX_MESSAGE_LENGTH: usize = 256 * 1024;
+
+struct Identity {
+    title: String,
+    key: PrivateKey,
+    public_key_blob: Vec<u8>,
+}
+
+struct Agent {
+    identities: Vec<Identity>,
+    confirm: bool,
+    /// Only one confirmation prompt can be shown at a time
+    prompt_lock: Mutex<()>,
+}
+
+pub fn run_ssh_agent(mut args: SshAgent) -> Result<(), anyhow::Error> {
+    let db: DatabaseFile = *Database::new(&mut args.path, args.database_name.as_str())?;
+    let identities = load_identities(&db);
+    drop(db);
+    if identities.is_empty() {
+        return Err(anyhow!("the vault doesn't contain any usable SSH keys"));
+    }
+
+    let listener = bind_socket(&args.socket)?;
+    println!(
+        "SSH_AUTH_SOCK={}; export SSH_AUTH_SOCK;",
+        args.socket.display()
+    );
+    eprintln!(
+        "Serving {} key(s): {}",
+        identities.len(),
+        identities
+            .iter()
+            .map(|x| x.title.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    if let Some(lifetime) = args.lifetime {
+        let socket = args.socket.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_secs(lifetime));
+            let _ = fs::remove_file(&socket);
+            eprintln!("Lifetime of {} seconds reached, stopping the agent", lifetime);
+            std::process::exit(0);
+        });
+    }
+
+    let agent = Arc::new(Agent {
+        identities,
+        confirm: args.confirm,
+        prompt_lock: Mutex::new(()),
+    });
+    for stream in listener.incoming() {
+        let stream = match stream {
+            std::result::Result::Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Failed to accept connection: {}", err);
+                continue;
+            }
+        };
+        let agent = Arc::clone(&agent);
+        thread::spawn(move || {
+            if let Err(err) = agent.serve_client(stream) {
+                eprintln!("Connection closed: {}", err);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Parses all SSH key entries, encrypted keys are decrypted with the passphrase field
+fn load_identities(db: &DatabaseFile) -> Vec<Identity> {
+    let mut identities = Vec::new();
+    for entry in db
+        .entries
+        .iter()
+        .filter(|x| x.entry_type == EntryType::SshKey)
+    {
+        let mut key = match PrivateKey::from_openssh(&entry.value) {
+            std::result::Result::Ok(key) => key,
+            Err(err) => {
+                eprintln!("Skipping '{}': {}", entry.title, err);
+                continue;
+            }
+        };
+        if key.is_encrypted() {
+            let passphrase = entry.get_field(
+                entry
+                    .entry_type
+                    .field_by_key("passphrase")
+                    .expect("ssh keys have a passphrase"),
+            );
+            key = match key.decrypt(passphrase) {
+                std::result::Result::Ok(key) => key,
+                Err(err) => {
+                    eprintln!("Skipping '{}', decrypting failed: {}", entry.title, err);
+                    continue;
+                }
+            };
+        }
+        let public_key_blob = match key.public_key().to_bytes() {
+            std::result::Result::Ok(blob) => blob,
+            Err(err) => {
+                eprintln!("Skipping '{}': {}", entry.title, err);
+                continue;
+            }
+        };
+        identities.push(Identity {
+            title: entry.title.clone(),
+            key,
+            public_key_blob,
+        });
+    }
+    identities
+}
+
+/// Binds the socket and restricts it to the current user, stale sockets are replaced
+fn bind_socket(socket: &Path) -> Result<UnixListener, anyhow::Error> {
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(anyhow!(
+                "an agent is already listening on {}",
+                socket.display()
+            ));
+        }
+        fs::remove_file(socket)?;
+    }
+    let listener = UnixListener::bind(socket)?;
+    fs::set_permissions(socket, fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+impl Agent {
+    fn serve_client(&self, mut stream: UnixStream) -> Result<(), anyhow::Error> {
+        loop {
+            let mut length = [0u8; 4];
+            if stream.read_exact(&mut length).is_err() {
+                // client closed the connection
+                return Ok(());
+            }
+            let length = u32::from_be_bytes(length) as usize;
+            if length == 0 || length > MAX_MESSAGE_LENGTH {
+                return Err(anyhow!("invalid message length {}", length));
+            }
+            let mut message = vec![0u8; length];
+            stream.read_exact(&mut message)?;
+
+            let response = self
+                .handle_message(&message)
+                .unwrap_or_else(|_| vec![SSH_AGENT_FAILURE]);
+            stream.write_all(&(response.len() as u32).to_be_bytes())?;
+            stream.write_all(&response)?;
+        }
+    }
+
+    fn handle_message(&self, message: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+        let mut reader = MessageReader { data: &message[1..] };
+        match message[0] {
+            SSH_AGENTC_REQUEST_IDENTITIES => {
+                let mut response = vec![SSH_AGENT_IDENTITIES_ANSWER];
+                put_u32(&mut response, self.identities.len() as u32);
+                for identity in self.identities.iter() {
+                    put_string(&mut response, &identity.public_key_blob);
+                    put_string(&mut response, identity.title.as_bytes());
+                }
+                Ok(response)
+            }
+            SSH_AGENTC_SIGN_REQUEST => {
+                let key_blob = reader.read_string()?;
+                let data = reader.read_string()?;
+                let flags = reader.read_u32()?;
+
+                let identity = self
+                    .identities
+                    .iter()
+                    .find(|x| x.public_key_blob == key_blob)
+                    .ok_or_else(|| anyhow!("unknown key"))?;
+                if self.confirm && !self.confirm_use(identity) {
+                    return Err(anyhow!("signing was denied"));
+                }
+
+                let mut signature_blob = Vec::new();
+                sign(&identity.key, data, flags)?.encode(&mut signature_blob)?;
+                let mut response = vec![SSH_AGENT_SIGN_RESPONSE];
+                put_string(&mut response, &signature_blob);
+                Ok(response)
+            }
+            // adding, removing and locking keys is done in the vault itself
+            _ => Ok(vec![SSH_AGENT_FAILURE]),
+        }
+    }
+
+    fn confirm_use(&self, identity: &Identity) -> bool {
+        let _guard = self.prompt_lock.lock().unwrap_or_else(|x| x.into_inner());
+        // without an input to ask nothing is signed
+        let answer = prompt_user(&format!(
+            "Allow signing with '{}'? [y/N] ",
+            identity.title
+        ))
+        .unwrap_or_default();
+        answer.trim().eq_ignore_ascii_case("y")
+    }
+}
+
+fn sign(key: &PrivateKey, data: &[u8], flags: u32) -> Result<Signature, anyhow::Error> {
+    match key.key_data() {
+        KeypairData::Rsa(keypair) => {
+            let private_key = rsa_private_key(keypair)?;
+            if flags & SSH_AGENT_RSA_SHA2_512 != 0 {
+                let signature = rsa::pkcs1v15::SigningKey::<Sha512>::new(private_key)
+                    .try_sign(data)?;
+                Ok(Signature::new(
+                    Algorithm::Rsa {
+                        hash: Some(HashAlg::Sha512),
+                    },
+                    signature.to_vec(),
+                )?)
+            } else if flags & SSH_AGENT_RSA_SHA2_256 != 0 {
+                let signature = rsa::pkcs1v15::SigningKey::<Sha256>::new(private_key)
+                    .try_sign(data)?;
+                Ok(Signature::new(
+                    Algorithm::Rsa {
+                        hash: Some(HashAlg::Sha256),
+                    },
+                    signature.to_vec(),
+                )?)
+            } else {
+                Err(anyhow!("SHA-1 signatures (ssh-rsa) are not supported"))
+            }
+        }
+        _ => Ok(key.try_sign(data)?),
+    }
+}
+
+/// The conversion of ssh-key passes the prime p twice, so the key is built from its components here
+fn rsa_private_key(keypair: &RsaKeypair) -> Result<rsa::RsaPrivateKey, anyhow::Error> {
+    let to_uint = |value: &Mpint| rsa::BigUint::try_from(value);
+    Ok(rsa::RsaPrivateKey::from_components(
+        to_uint(&keypair.public.n)?,
+        to_uint(&keypair.public.e)?,
+        to_uint(&keypair.private.d)?,
+        vec![to_uint(&keypair.private.p)?, to_uint(&keypair.private.q)?],
+    )?)
+}
+
+struct MessageReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> MessageReader<'a> {
+    fn read_u32(&mut self) -> Result<u32, anyhow::Error> {
+        if self.data.len() < 4 {
+            return Err(anyhow!("message is too short"));
+        }
+        let (value, rest) = self.data.split_at(4);
+        self.data = rest;
+        Ok(u32::from_be_bytes([value[0], value[1], value[2], value[3]]))
+    }
+
+    fn read_string(&mut self) -> Result<&'a [u8], anyhow::Error> {
+        let length = self.read_u32()? as usize;
+        if self.data.len() < length {
+            return Err(anyhow!("message is too short"));
+        }
+        let (value, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(value)
+    }
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_string(buffer: &mut Vec<u8>, value: &[u8]) {
+    put_u32(buffer, value.len() as u32);
+    buffer.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::structures::Config;
+    use crate::encryption_and_decryption::argon::KdfParams;
+    use chrono::Local;
+    use rand::rngs::OsRng;
+    use rsa::signature::Verifier;
+    use ssh_key::private::RsaKeypair;
+    use ssh_key::{EcdsaCurve, LineEnding};
+
+    /// Agent serving SSH key entries with the given titles, keys and passphrases
+    fn agent(keys: &[(&str, &PrivateKey, &str)]) -> Agent {
+        let mut db = DatabaseFile {
+            entries: Vec::new(),
+            recycle_bin: Vec::new(),
+            tombstones: Vec::new(),
+            config: Config {
+                comment: String::new(),
+                author: String::from("test"),
+                recycle_bin_retention_days: 30,
+                sync_target: None,
+            },
+            last_access: Local::now(),
+            password: String::new(),
+            file_state: None,
+            kdf: KdfParams::default(),
+        };
+        for (title, key, passphrase) in keys {
+            let id = db.add_empty_entry();
+            let entry = db.entries.iter_mut().find(|x| x.id == id).unwrap();
+            entry.entry_type = EntryType::SshKey;
+            entry.title = title.to_string();
+            for (field, value) in [
+                (
+                    "private_key",
+                    key.to_openssh(LineEnding::LF).unwrap().to_string(),
+                ),
+                ("passphrase", passphrase.to_string()),
+            ] {
+                let spec = entry.entry_type.field_by_key(field).unwrap();
+                entry.set_field(spec, value).unwrap();
+            }
+        }
+        Agent {
+            identities: load_identities(&db),
+            confirm: false,
+            prompt_lock: Mutex::new(()),
+        }
+    }
+
+    fn sign_request(key: &PrivateKey, data: &[u8], flags: u32) -> Vec<u8> {
+        let mut request = vec![SSH_AGENTC_SIGN_REQUEST];
+        put_string(&mut request, &key.public_key().to_bytes().unwrap());
+        put_string(&mut request, data);
+        put_u32(&mut request, flags);
+        request
+    }
+
+    /// Signs `data` through the agent and checks the signature with the public key
+    fn assert_signs(agent: &Agent, key: &PrivateKey, flags: u32) -> Signature {
+        let data = b"session data";
+        let response = agent
+            .handle_message(&sign_request(key, data, flags))
+            .unwrap();
+        assert_eq!(response[0], SSH_AGENT_SIGN_RESPONSE);
+        let mut reader = MessageReader {
+            data: &response[1..],
+        };
+        let signature = Signature::try_from(reader.read_string().unwrap()).unwrap();
+        key.public_key()
+            .key_data()
+            .verify(data, &signature)
+            .unwrap();
+        signature
+    }
+
+    #[test]
+    fn lists_the_usable_keys_of_the_vault() {
+        let ed25519 = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+        let ecdsa = PrivateKey::random(
+            &mut OsRng,
+            Algorithm::Ecdsa {
+                curve: EcdsaCurve::NistP256,
+            },
+        )
+        .unwrap();
+        let encrypted = ecdsa.encrypt(&mut OsRng, "secret").unwrap();
+        let agent = agent(&[
+            ("laptop", &ed25519, ""),
+            ("server", &encrypted, "secret"),
+            ("wrong passphrase", &encrypted, "guess"),
+        ]);
+
+        let response = agent
+            .handle_message(&[SSH_AGENTC_REQUEST_IDENTITIES])
+            .unwrap();
+        assert_eq!(response[0], SSH_AGENT_IDENTITIES_ANSWER);
+        let mut reader = MessageReader {
+            data: &response[1..],
+        };
+        assert_eq!(reader.read_u32().unwrap(), 2);
+        for (title, key) in [("laptop", &ed25519), ("server", &ecdsa)] {
+            assert_eq!(
+                reader.read_string().unwrap(),
+                key.public_key().to_bytes().unwrap().as_slice()
+            );
+            assert_eq!(reader.read_string().unwrap(), title.as_bytes());
+        }
+        assert!(reader.data.is_empty());
+    }
+
+    #[test]
+    fn signs_with_ed25519_and_ecdsa_keys() {
+        let ed25519 = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+        let ecdsa = PrivateKey::random(
+            &mut OsRng,
+            Algorithm::Ecdsa {
+                curve: EcdsaCurve::NistP384,
+            },
+        )
+        .unwrap();
+        let agent = agent(&[("laptop", &ed25519, ""), ("server", &ecdsa, "")]);
+        assert_signs(&agent, &ed25519, 0);
+        assert_signs(&agent, &ecdsa, 0);
+
+        let unknown = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+        assert!(agent
+            .handle_message(&sign_request(&unknown, b"data", 0))
+            .is_err());
+    }
+
+    #[test]
+    fn signs_with_rsa_keys_using_sha2() {
+        let rsa = PrivateKey::from(RsaKeypair::random(&mut OsRng, 2048).unwrap());
+        let agent = agent(&[("legacy", &rsa, "")]);
+        let sha512 = assert_signs(&agent, &rsa, SSH_AGENT_RSA_SHA2_512);
+        assert_eq!(sha512.algorithm().as_str(), "rsa-sha2-512");
+        let sha256 = assert_signs(&agent, &rsa, SSH_AGENT_RSA_SHA2_256);
+        assert_eq!(sha256.algorithm().as_str(), "rsa-sha2-256");
+        assert!(agent
+            .handle_message(&sign_request(&rsa, b"data", 0))
+            .is_err());
+    }
+}
@@ -0,0 +1,328 @@
+use anyhow::{anyhow, Ok};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use uuid::Uuid;
+
+use crate::commands::merge::load_with_password;
+use crate::database::lock::VaultLock;
+use crate::database::merge::{merge_databases, newest_side, MergeReport};
+use crate::database::operations::{resolve_database_path, Database};
+use crate::database::structures::{DatabaseFile, SyncTarget};
+use crate::utils::host::host_name;
+use crate::Sync;
+
+/// How often a rejected git push is retried with the newer remote state
+const PUSH_ATTEMPTS: usize = 3;
+
+/// Pulls the vault from the sync target, merges it with the local one and pushes the result back
+pub fn sync_vault(mut args: Sync) -> Result<(), anyhow::Error> {
+    let path = resolve_database_path(&args.path, &args.database_name)?;
+    let _lock = VaultLock::acquire_for_writing(&path)?;
+    args.path = Some(path.clone());
+    let db = *DatabaseFile::new(&mut args.path, &args.database_name)?;
+    let new_target = match (args.dir.take(), args.git.take()) {
+        (Some(dir), _) => Some(SyncTarget::Directory(fs::canonicalize(dir)?)),
+        (None, Some(url)) => Some(SyncTarget::Git(url)),
+        (None, None) => None,
+    };
+    sync_database(db, &path, new_target)
+}
+
+/// Syncs the loaded vault stored at `path`, `new_target` replaces the sync target configured in the vault
+fn sync_database(
+    mut db: DatabaseFile,
+    path: &Path,
+    new_target: Option<SyncTarget>,
+) -> Result<(), anyhow::Error> {
+    let path = path.to_path_buf();
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file", path.display()))?
+        .to_owned();
+    let base_path = path.with_file_name(format!(".{}.sync-base", file_name.to_string_lossy()));
+
+    let mut local_changed = false;
+    if let Some(target) = new_target {
+        db.config.sync_target = Some(target);
+        local_changed = true;
+    }
+    let target = db.config.sync_target.clone().ok_or_else(|| {
+        anyhow!("no sync target is configured yet, please set one with --dir or --git")
+    })?;
+
+    let base = if base_path.exists() {
+        load_with_password(&base_path, &db.password).ok()
+    } else {
+        None
+    };
+
+    match target {
+        SyncTarget::Directory(dir) => {
+            let remote_path = dir.join(&file_name);
+            let (merged, report, remote_changed) = merge_remote(&db, &remote_path, base.as_ref())?;
+            if !merged.same_content(&db) {
+                local_changed = true;
+            }
+            if local_changed {
+                merged.save_database(&Some(path.clone()))?;
+            }
+            if remote_changed {
+                copy_atomically(&path, &remote_path)?;
+            }
+            fs::copy(&path, &base_path)?;
+            print_report(&report, &dir.display().to_string());
+        }
+        SyncTarget::Git(url) => {
+            for attempt in 1..=PUSH_ATTEMPTS {
+                let checkout = GitCheckout::clone(&url)?;
+                let remote_path = checkout.dir.join(&file_name);
+                let (merged, report, remote_changed) =
+                    merge_remote(&db, &remote_path, base.as_ref())?;
+                if !merged.same_content(&db) {
+                    local_changed = true;
+                }
+                if local_changed {
+                    merged.save_database(&Some(path.clone()))?;
+                    db = merged;
+                }
+                if remote_changed {
+                    fs::copy(&path, &remote_path)?;
+                }
+                if checkout.commit(&file_name.to_string_lossy())? && !checkout.push()? {
+                    if attempt == PUSH_ATTEMPTS {
+                        return Err(anyhow!(
+                            "{} was changed by someone else while syncing, please try again",
+                            url
+                        ));
+                    }
+                    // the local vault already contains the merge, it is merged again with the newer remote
+                    local_changed = false;
+                    continue;
+                }
+                fs::copy(&path, &base_path)?;
+                print_report(&report, &url);
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Merges the remote copy into the local vault, conflicts keep the entry changed last.
+/// Returns if the remote copy misses anything of the merge and has to be replaced
+fn merge_remote(
+    local: &DatabaseFile,
+    remote_path: &Path,
+    base: Option<&DatabaseFile>,
+) -> Result<(DatabaseFile, MergeReport, bool), anyhow::Error> {
+    if !remote_path.exists() {
+        return Ok((local.clone(), MergeReport::default(), true));
+    }
+    let remote = load_with_password(remote_path, &local.password)?;
+    let (merged, report) =
+        merge_databases(local, &remote, base, |conflict| Ok(newest_side(conflict)))?;
+    let remote_changed = !merged.same_content(&remote);
+    Ok((merged, report, remote_changed))
+}
+
+/// Writes next to the destination first, so a reader of the directory never sees half a vault
+fn copy_atomically(source: &Path, destination: &Path) -> Result<(), anyhow::Error> {
+    let temporary = destination.with_extension("sync-tmp");
+    fs::copy(source, &temporary)?;
+    fs::rename(&temporary, destination)?;
+    Ok(())
+}
+
+fn print_report(report: &MergeReport, target: &str) {
+    println!(
+        "Synced with {}: {} local changes, {} remote changes, {} conflicts",
+        target, report.from_a, report.from_b, report.conflicts
+    );
+}
+
+/// Temporary clone of the sync repository, it is removed again on drop
+struct GitCheckout {
+    dir: PathBuf,
+}
+
+impl GitCheckout {
+    fn clone(url: &str) -> Result<GitCheckout, anyhow::Error> {
+        let dir = std::env::temp_dir().join(format!("vault-sync-{}", Uuid::new_v4()));
+        let checkout = GitCheckout { dir };
+        let output = Command::new("git")
+            .args(["clone", "--quiet", url])
+            .arg(&checkout.dir)
+            .output()
+            .map_err(|err| anyhow!("git could not be started: {}", err))?;
+        check(&output, "clone")?;
+        Ok(checkout)
+    }
+
+    fn git(&self, args: &[&str]) -> Result<Output, anyhow::Error> {
+        Ok(Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .output()?)
+    }
+
+    /// Commits the vault file, returns false if nothing changed
+    fn commit(&self, file_name: &str) -> Result<bool, anyhow::Error> {
+        check(&self.git(&["add", "--", file_name])?, "add")?;
+        if self.git(&["diff", "--cached", "--quiet"])?.status.success() {
+            return Ok(false);
+        }
+        let message = format!("Sync {} from {}", file_name, host_name());
+        let mut args = vec![];
+        // a fresh machine may have no git identity, the commit shouldn't fail because of it
+        let has_identity = self.git(&["config", "user.email"])?.status.success();
+        let email = format!("vault@{}", host_name());
+        let email_config = format!("user.email={}", email);
+        if !has_identity {
+            args.extend(["-c", "user.name=vault", "-c", email_config.as_str()]);
+        }
+        args.extend(["commit", "--quiet", "-m", message.as_str()]);
+        check(&self.git(&args)?, "commit")?;
+        Ok(true)
+    }
+
+    /// Pushes the current branch, returns false if the remote has newer commits
+    fn push(&self) -> Result<bool, anyhow::Error> {
+        let branch = self.git(&["symbolic-ref", "--short", "HEAD"])?;
+        check(&branch, "symbolic-ref")?;
+        let branch = String::from_utf8_lossy(&branch.stdout).trim().to_owned();
+        let output = self.git(&[
+            "push",
+            "--quiet",
+            "origin",
+            &format!("HEAD:refs/heads/{}", branch),
+        ])?;
+        if output.status.success() {
+            return Ok(true);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("[rejected]") || stderr.contains("fetch first") {
+            return Ok(false);
+        }
+        Err(anyhow!("git push failed: {}", stderr.trim()))
+    }
+}
+
+impl Drop for GitCheckout {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn check(output: &Output, command: &str) -> Result<(), anyhow::Error> {
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "git {} failed: {}",
+        command,
+        String::from_utf8_lossy(&output.stderr).trim()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::structures::Config;
+    use crate::encryption_and_decryption::argon::KdfParams;
+    use chrono::Local;
+
+    const PASSWORD: &str = "pw";
+
+    /// Vault with cheap KDF parameters so the many loads and saves of a sync stay fast
+    fn new_vault(path: &Path, titles: &[&str]) {
+        let mut db = DatabaseFile {
+            entries: Vec::new(),
+            recycle_bin: Vec::new(),
+            tombstones: Vec::new(),
+            config: Config {
+                comment: String::new(),
+                author: String::from("test"),
+                recycle_bin_retention_days: 30,
+                sync_target: None,
+            },
+            last_access: Local::now(),
+            password: String::from(PASSWORD),
+            file_state: None,
+            kdf: KdfParams {
+                memory_kib: 64,
+                iterations: 1,
+                parallelism: 1,
+            },
+        };
+        for title in titles {
+            add_entry(&mut db, title);
+        }
+        db.save_database(&Some(path.to_path_buf())).unwrap();
+    }
+
+    fn add_entry(db: &mut DatabaseFile, title: &str) {
+        let id = db.add_empty_entry();
+        if let Some(entry) = db.entries.iter_mut().find(|x| x.id == id) {
+            entry.title = title.to_owned();
+        }
+    }
+
+    fn load(path: &Path) -> DatabaseFile {
+        *DatabaseFile::load(path, PASSWORD).unwrap()
+    }
+
+    fn titles(path: &Path) -> Vec<String> {
+        let mut titles: Vec<String> = load(path).entries.iter().map(|x| x.title.clone()).collect();
+        titles.sort();
+        titles
+    }
+
+    fn edit(path: &Path, title: &str) {
+        let mut db = load(path);
+        add_entry(&mut db, title);
+        db.save_database(&Some(path.to_path_buf())).unwrap();
+    }
+
+    fn sync(path: &Path, target: Option<SyncTarget>) {
+        sync_database(load(path), path, target).unwrap();
+    }
+
+    #[test]
+    fn syncs_divergent_copies_through_a_bare_git_repository() {
+        let dir = std::env::temp_dir().join(format!("vault-sync-test-{}", Uuid::new_v4()));
+        let remote = dir.join("remote.git");
+        fs::create_dir_all(dir.join("a")).unwrap();
+        fs::create_dir_all(dir.join("b")).unwrap();
+        let init = Command::new("git")
+            .args(["init", "--quiet", "--bare"])
+            .arg(&remote)
+            .output()
+            .unwrap();
+        check(&init, "init").unwrap();
+
+        // the first machine publishes the vault, the second starts from a copy of it
+        let a = dir.join("a").join("team.vault");
+        let b = dir.join("b").join("team.vault");
+        new_vault(&a, &["shared"]);
+        sync(&a, Some(SyncTarget::Git(remote.display().to_string())));
+        fs::copy(&a, &b).unwrap();
+        sync(&b, None);
+
+        edit(&a, "from a");
+        edit(&b, "from b");
+        sync(&a, None);
+        sync(&b, None);
+        sync(&a, None);
+
+        let merged = vec!["from a", "from b", "shared"];
+        assert_eq!(titles(&a), merged);
+        assert_eq!(titles(&b), merged);
+        for path in [&a, &b] {
+            let base = path.with_file_name(".team.vault.sync-base");
+            assert_eq!(titles(&base), merged);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
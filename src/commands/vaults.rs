@@ -0,0 +1,92 @@
+use anyhow::{anyhow, Ok};
+use std::path::PathBuf;
+
+use crate::database::operations::resolve_database_path;
+use crate::utils::registry::{KnownVault, Registry};
+use crate::utils::terminal_interactions::prompt_user;
+use crate::{Alias, LsVaults};
+
+/// Prints all known vaults, `--prune` forgets the ones which don't exist anymore
+pub fn list_vaults(args: LsVaults) -> Result<(), anyhow::Error> {
+    let mut registry = Registry::load()?;
+    if args.prune {
+        for missing in registry.prune() {
+            println!("Removed {}", missing.path.display());
+        }
+        registry.save()?;
+    }
+    for known in registry.vaults.iter() {
+        println!("{}", describe(known));
+    }
+    Ok(())
+}
+
+/// Gives a vault a short name which can be used instead of its path, e.g. `vault open -d work`
+pub fn alias_vault(args: Alias) -> Result<(), anyhow::Error> {
+    let mut registry = Registry::load()?;
+    if args.remove {
+        if !registry.remove_alias(&args.alias) {
+            return Err(anyhow!("no vault has the alias '{}'", args.alias));
+        }
+        return registry.save();
+    }
+    let path = match (&args.path, &args.database_name) {
+        (None, None) => {
+            return Err(anyhow!(
+                "please name the vault with --database-name or --path"
+            ))
+        }
+        (path, name) => resolve_database_path(path, name.as_deref().unwrap_or_default())?,
+    };
+    if !path.exists() {
+        return Err(anyhow!("{} doesn't exist", path.display()));
+    }
+    registry.set_alias(&path, &args.alias)?;
+    registry.save()
+}
+
+/// Lets the user choose one of the known vaults by number or alias, or enter any path
+pub fn pick_vault() -> Result<PathBuf, anyhow::Error> {
+    let registry = Registry::load()?;
+    if !registry.vaults.is_empty() {
+        println!("Known vaults:");
+        for (index, known) in registry.vaults.iter().enumerate() {
+            println!("{:>3}  {}", index + 1, describe(known));
+        }
+    }
+    loop {
+        let answer = prompt_user("Please select a vault by number or alias, or enter a path:")?;
+        let answer = answer.trim();
+        if answer.is_empty() {
+            continue;
+        }
+        if let Some(known) = answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|x| x.checked_sub(1))
+            .and_then(|x| registry.vaults.get(x))
+            .or_else(|| registry.find_alias(answer))
+        {
+            return Ok(known.path.clone());
+        }
+        let path = PathBuf::from(answer);
+        if path.exists() {
+            return Ok(path);
+        }
+        println!("{} is neither a known vault nor an existing file", answer);
+    }
+}
+
+fn describe(known: &KnownVault) -> String {
+    let last_opened = known
+        .last_opened
+        .map(|x| x.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| String::from("never opened"));
+    format!(
+        "{:<12} {}  ({}){}",
+        known.alias.as_deref().unwrap_or("-"),
+        known.path.display(),
+        last_opened,
+        if known.path.exists() { "" } else { "  missing" }
+    )
+}
@@ -0,0 +1,514 @@
+use anyhow::anyhow;
+use chrono::NaiveDate;
+
+use super::structures::{EntryType, ExtraField, PasswordEntry};
+
+/// Place inside a `PasswordEntry` where the value of a field is stored
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FieldSlot {
+    Title,
+    Name,
+    Value,
+    Url,
+    Comment,
+    Extra(&'static str),
+}
+
+pub type Validator = fn(&str) -> Result<(), anyhow::Error>;
+
+pub struct FieldSpec {
+    /// Name used on the command line
+    pub key: &'static str,
+    /// Name shown in the TUI
+    pub label: &'static str,
+    pub slot: FieldSlot,
+    /// Hidden until the user explicitly shows it
+    pub secret: bool,
+    pub multiline: bool,
+    pub validator: Option<Validator>,
+}
+
+const fn field(key: &'static str, label: &'static str, slot: FieldSlot) -> FieldSpec {
+    FieldSpec {
+        key,
+        label,
+        slot,
+        secret: false,
+        multiline: false,
+        validator: None,
+    }
+}
+
+const fn secret(key: &'static str, label: &'static str, slot: FieldSlot) -> FieldSpec {
+    FieldSpec {
+        secret: true,
+        ..field(key, label, slot)
+    }
+}
+
+const fn multiline(key: &'static str, label: &'static str, slot: FieldSlot) -> FieldSpec {
+    FieldSpec {
+        multiline: true,
+        ..field(key, label, slot)
+    }
+}
+
+static CLASSIC_PASSWORD_FIELDS: [FieldSpec; 6] = [
+    field("title", "Title", FieldSlot::Title),
+    field("username", "Username", FieldSlot::Name),
+    secret("password", "Password", FieldSlot::Value),
+    field("url", "Url", FieldSlot::Url),
+    FieldSpec {
+        validator: Some(validate_totp),
+        ..secret("totp", "TOTP", FieldSlot::Extra("totp"))
+    },
+    multiline("comment", "Comment", FieldSlot::Comment),
+];
+
+static ENVIRONMENT_VARIABLE_FIELDS: [FieldSpec; 4] = [
+    field("title", "Title", FieldSlot::Title),
+    field("variable", "Variable", FieldSlot::Name),
+    secret("value", "Value", FieldSlot::Value),
+    multiline("comment", "Comment", FieldSlot::Comment),
+];
+
+static SSH_KEY_FIELDS: [FieldSpec; 6] = [
+    field("title", "Title", FieldSlot::Title),
+    FieldSpec {
+        validator: Some(validate_ssh_private_key),
+        multiline: true,
+        ..secret("private_key", "Private Key", FieldSlot::Value)
+    },
+    FieldSpec {
+        validator: Some(validate_ssh_public_key),
+        ..field("public_key", "Public Key", FieldSlot::Extra("public_key"))
+    },
+    secret("passphrase", "Passphrase", FieldSlot::Extra("passphrase")),
+    field("host", "Host", FieldSlot::Url),
+    multiline("comment", "Comment", FieldSlot::Comment),
+];
+
+static PAYMENT_CARD_FIELDS: [FieldSpec; 6] = [
+    field("title", "Title", FieldSlot::Title),
+    field("cardholder", "Cardholder", FieldSlot::Name),
+    FieldSpec {
+        validator: Some(validate_card_number),
+        ..secret("number", "Card Number", FieldSlot::Value)
+    },
+    FieldSpec {
+        validator: Some(validate_card_expiry),
+        ..field("expiry", "Expiry (MM/YY)", FieldSlot::Extra("expiry"))
+    },
+    FieldSpec {
+        validator: Some(validate_card_cvv),
+        ..secret("cvv", "CVV", FieldSlot::Extra("cvv"))
+    },
+    multiline("comment", "Comment", FieldSlot::Comment),
+];
+
+static SECURE_NOTE_FIELDS: [FieldSpec; 2] = [
+    field("title", "Title", FieldSlot::Title),
+    FieldSpec {
+        secret: true,
+        ..multiline("note", "Note", FieldSlot::Comment)
+    },
+];
+
+static IDENTITY_FIELDS: [FieldSpec; 8] = [
+    field("title", "Title", FieldSlot::Title),
+    field("full_name", "Full Name", FieldSlot::Name),
+    FieldSpec {
+        validator: Some(validate_email),
+        ..field("email", "Email", FieldSlot::Extra("email"))
+    },
+    field("phone", "Phone", FieldSlot::Extra("phone")),
+    multiline("address", "Address", FieldSlot::Extra("address")),
+    FieldSpec {
+        validator: Some(validate_date),
+        ..field("birthday", "Birthday (YYYY-MM-DD)", FieldSlot::Extra("birthday"))
+    },
+    field("website", "Website", FieldSlot::Url),
+    multiline("comment", "Comment", FieldSlot::Comment),
+];
+
+impl EntryType {
+    pub const ALL: [EntryType; 6] = [
+        EntryType::ClassicPassword,
+        EntryType::EnvironmentVariable,
+        EntryType::SshKey,
+        EntryType::PaymentCard,
+        EntryType::SecureNote,
+        EntryType::Identity,
+    ];
+
+    pub fn fields(&self) -> &'static [FieldSpec] {
+        match self {
+            EntryType::ClassicPassword => &CLASSIC_PASSWORD_FIELDS,
+            EntryType::EnvironmentVariable => &ENVIRONMENT_VARIABLE_FIELDS,
+            EntryType::SshKey => &SSH_KEY_FIELDS,
+            EntryType::PaymentCard => &PAYMENT_CARD_FIELDS,
+            EntryType::SecureNote => &SECURE_NOTE_FIELDS,
+            EntryType::Identity => &IDENTITY_FIELDS,
+        }
+    }
+
+    /// Name used on the command line
+    pub fn key(&self) -> &'static str {
+        match self {
+            EntryType::ClassicPassword => "password",
+            EntryType::EnvironmentVariable => "env",
+            EntryType::SshKey => "ssh-key",
+            EntryType::PaymentCard => "card",
+            EntryType::SecureNote => "note",
+            EntryType::Identity => "identity",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EntryType::ClassicPassword => "Password",
+            EntryType::EnvironmentVariable => "Environment Variable",
+            EntryType::SshKey => "SSH Key",
+            EntryType::PaymentCard => "Payment Card",
+            EntryType::SecureNote => "Secure Note",
+            EntryType::Identity => "Identity",
+        }
+    }
+
+    pub fn next(&self) -> EntryType {
+        let position = EntryType::ALL.iter().position(|x| x == self).unwrap_or(0);
+        EntryType::ALL[(position + 1) % EntryType::ALL.len()].clone()
+    }
+
+    /// Field printed by `get` without `--field`: the first secret like the password or the note, types
+    /// without secrets use their first field after the title
+    pub fn primary_field(&self) -> &'static FieldSpec {
+        let fields = self.fields();
+        fields
+            .iter()
+            .find(|x| x.secret)
+            .or_else(|| fields.get(1))
+            .unwrap_or(&fields[0])
+    }
+
+    /// Finds a field by its command line name, the generic names title, name, value, url and comment
+    /// are accepted for every entry type
+    pub fn field_by_key(&self, key: &str) -> Result<&'static FieldSpec, anyhow::Error> {
+        let fields = self.fields();
+        let generic_slot = match key {
+            "title" => Some(FieldSlot::Title),
+            "name" => Some(FieldSlot::Name),
+            "value" => Some(FieldSlot::Value),
+            "url" => Some(FieldSlot::Url),
+            "comment" => Some(FieldSlot::Comment),
+            _ => None,
+        };
+        fields
+            .iter()
+            .find(|x| x.key == key)
+            .or_else(|| fields.iter().find(|x| Some(x.slot) == generic_slot))
+            .ok_or_else(|| {
+                anyhow!(
+                    "unknown field '{}' for {} entries, available fields: {}",
+                    key,
+                    self.key(),
+                    fields.iter().map(|x| x.key).collect::<Vec<_>>().join(", ")
+                )
+            })
+    }
+}
+
+impl PasswordEntry {
+    pub fn fields(&self) -> &'static [FieldSpec] {
+        self.entry_type.fields()
+    }
+
+    pub fn get_field(&self, spec: &FieldSpec) -> String {
+        match spec.slot {
+            FieldSlot::Title => self.title.clone(),
+            FieldSlot::Name => self.name.clone(),
+            FieldSlot::Value => self.value.clone(),
+            FieldSlot::Url => self.url.clone(),
+            FieldSlot::Comment => self.comment.clone(),
+            FieldSlot::Extra(key) => self
+                .extra_fields
+                .iter()
+                .find(|x| x.key == key)
+                .map(|x| x.value.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Validates and stores the value, empty values are always accepted
+    pub fn set_field(&mut self, spec: &FieldSpec, value: String) -> Result<(), anyhow::Error> {
+        if !value.is_empty() {
+            if let Some(validator) = spec.validator {
+                validator(&value).map_err(|err| anyhow!("{}: {}", spec.label, err))?;
+            }
+        }
+
+        match spec.slot {
+            FieldSlot::Title => self.title = value,
+            FieldSlot::Name => self.name = value,
+            FieldSlot::Value => self.value = value,
+            FieldSlot::Url => self.url = value,
+            FieldSlot::Comment => self.comment = value,
+            FieldSlot::Extra(key) => match self.extra_fields.iter_mut().find(|x| x.key == key) {
+                Some(extra_field) => extra_field.value = value,
+                None => self.extra_fields.push(ExtraField {
+                    key: key.to_owned(),
+                    value,
+                }),
+            },
+        }
+
+        if self.entry_type == EntryType::SshKey && spec.slot == FieldSlot::Value {
+            self.update_ssh_public_key();
+        }
+        Ok(())
+    }
+
+    /// What switching to `entry_type` would do to the stored values: secrets which would be shown unmasked,
+    /// values no field of the new type shows and values the new type rejects
+    pub fn type_change_issues(&self, entry_type: &EntryType) -> Vec<String> {
+        let mut issues = Vec::new();
+        for field in self.fields() {
+            let value = self.get_field(field);
+            if value.is_empty() {
+                continue;
+            }
+            match entry_type.fields().iter().find(|x| x.slot == field.slot) {
+                None => issues.push(format!("{} would be hidden", field.label)),
+                Some(new_field) => {
+                    if field.secret && !new_field.secret {
+                        issues.push(format!("{} would be shown unmasked as {}", field.label, new_field.label));
+                    }
+                    if let Some(err) = new_field.validator.and_then(|x| x(&value).err()) {
+                        issues.push(format!("{} is no valid {}: {}", field.label, new_field.label, err));
+                    }
+                }
+            }
+        }
+        issues
+    }
+
+    /// Derives the public key from a new private key, so the key the SSH agent offers always fits it
+    fn update_ssh_public_key(&mut self) {
+        let public_key_spec = match self.entry_type.field_by_key("public_key") {
+            Ok(spec) => spec,
+            Err(_) => return,
+        };
+        let public_key = match ssh_key::PrivateKey::from_openssh(&self.value) {
+            Ok(private_key) => private_key.public_key().to_openssh().unwrap_or_default(),
+            Err(_) => String::new(),
+        };
+        let _ = self.set_field(public_key_spec, public_key);
+    }
+}
+
+fn validate_ssh_private_key(value: &str) -> Result<(), anyhow::Error> {
+    ssh_key::PrivateKey::from_openssh(value)
+        .map(|_| ())
+        .map_err(|err| anyhow!("not an OpenSSH private key ({})", err))
+}
+
+fn validate_ssh_public_key(value: &str) -> Result<(), anyhow::Error> {
+    ssh_key::PublicKey::from_openssh(value)
+        .map(|_| ())
+        .map_err(|err| anyhow!("not an OpenSSH public key ({})", err))
+}
+
+/// Accepts `otpauth://` URIs and plain base32 secrets
+fn validate_totp(value: &str) -> Result<(), anyhow::Error> {
+    if value.starts_with("otpauth://") {
+        return Ok(());
+    }
+    let is_base32 = value
+        .chars()
+        .filter(|x| *x != ' ' && *x != '=')
+        .all(|x| x.is_ascii_alphabetic() || ('2'..='7').contains(&x));
+    if !is_base32 {
+        return Err(anyhow!("expected an otpauth:// URI or a base32 secret"));
+    }
+    Ok(())
+}
+
+/// Checks the length and the Luhn checksum, spaces and dashes are ignored
+fn validate_card_number(value: &str) -> Result<(), anyhow::Error> {
+    let digits: Vec<u32> = value
+        .chars()
+        .filter(|x| *x != ' ' && *x != '-')
+        .map(|x| x.to_digit(10).ok_or_else(|| anyhow!("only digits are allowed")))
+        .collect::<Result<_, _>>()?;
+    if !(12..=19).contains(&digits.len()) {
+        return Err(anyhow!("must have between 12 and 19 digits"));
+    }
+
+    let checksum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| {
+            if index % 2 == 1 {
+                let doubled = digit * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                *digit
+            }
+        })
+        .sum();
+    if !checksum.is_multiple_of(10) {
+        return Err(anyhow!("checksum is invalid, please check for typos"));
+    }
+    Ok(())
+}
+
+fn validate_card_expiry(value: &str) -> Result<(), anyhow::Error> {
+    let (month, year) = value
+        .split_once('/')
+        .ok_or_else(|| anyhow!("expected the format MM/YY"))?;
+    let month: u32 = month.trim().parse().map_err(|_| anyhow!("month is not a number"))?;
+    if !(1..=12).contains(&month) {
+        return Err(anyhow!("month must be between 1 and 12"));
+    }
+    let year = year.trim();
+    if !(year.len() == 2 || year.len() == 4) || year.parse::<u32>().is_err() {
+        return Err(anyhow!("year must have two or four digits"));
+    }
+    Ok(())
+}
+
+fn validate_card_cvv(value: &str) -> Result<(), anyhow::Error> {
+    if !(3..=4).contains(&value.len()) || !value.chars().all(|x| x.is_ascii_digit()) {
+        return Err(anyhow!("must be three or four digits"));
+    }
+    Ok(())
+}
+
+fn validate_email(value: &str) -> Result<(), anyhow::Error> {
+    match value.split_once('@') {
+        Some((local, domain)) if !local.is_empty() && domain.contains('.') => Ok(()),
+        _ => Err(anyhow!("not a valid email address")),
+    }
+}
+
+fn validate_date(value: &str) -> Result<(), anyhow::Error> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|_| ())
+        .map_err(|_| anyhow!("expected the format YYYY-MM-DD"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+    use rand::rngs::OsRng;
+    use ssh_key::{Algorithm, LineEnding, PrivateKey};
+
+    fn entry(entry_type: EntryType) -> PasswordEntry {
+        PasswordEntry {
+            id: String::from("id"),
+            title: String::from("title"),
+            name: String::new(),
+            value: String::new(),
+            url: String::new(),
+            comment: String::new(),
+            entry_type,
+            last_modified: Local::now().to_string(),
+            files: Vec::new(),
+            extra_fields: Vec::new(),
+            group: String::new(),
+        }
+    }
+
+    fn new_key() -> (String, String) {
+        let key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+        let private_key = key.to_openssh(LineEnding::LF).unwrap().to_string();
+        (private_key, key.public_key().to_openssh().unwrap())
+    }
+
+    #[test]
+    fn derives_the_public_key_of_every_new_private_key() {
+        let mut entry = entry(EntryType::SshKey);
+        let private_key = entry.entry_type.field_by_key("private_key").unwrap();
+        let public_key = entry.entry_type.field_by_key("public_key").unwrap();
+        let (first, first_public) = new_key();
+        let (second, second_public) = new_key();
+
+        entry.set_field(private_key, first).unwrap();
+        assert_eq!(entry.get_field(public_key), first_public);
+        entry.set_field(private_key, second).unwrap();
+        assert_eq!(entry.get_field(public_key), second_public);
+        entry.set_field(private_key, String::new()).unwrap();
+        assert_eq!(entry.get_field(public_key), "");
+    }
+
+    #[test]
+    fn checks_card_numbers_with_luhn() {
+        assert!(validate_card_number("4111 1111 1111 1111").is_ok());
+        assert!(validate_card_number("5555-5555-5555-4444").is_ok());
+        assert!(validate_card_number("4111 1111 1111 1112").is_err());
+        assert!(validate_card_number("4111 1111 111a 1111").is_err());
+        assert!(validate_card_number("42").is_err());
+    }
+
+    #[test]
+    fn checks_card_expiry_and_cvv() {
+        assert!(validate_card_expiry("01/29").is_ok());
+        assert!(validate_card_expiry("12/2030").is_ok());
+        assert!(validate_card_expiry("13/29").is_err());
+        assert!(validate_card_expiry("0129").is_err());
+        assert!(validate_card_expiry("01/299").is_err());
+        assert!(validate_card_cvv("123").is_ok());
+        assert!(validate_card_cvv("1234").is_ok());
+        assert!(validate_card_cvv("12").is_err());
+        assert!(validate_card_cvv("12a").is_err());
+    }
+
+    #[test]
+    fn checks_emails_dates_and_totp_secrets() {
+        assert!(validate_email("me@example.com").is_ok());
+        assert!(validate_email("@example.com").is_err());
+        assert!(validate_email("me@localhost").is_err());
+        assert!(validate_date("1990-02-28").is_ok());
+        assert!(validate_date("1990-02-30").is_err());
+        assert!(validate_date("28.02.1990").is_err());
+        assert!(validate_totp("otpauth://totp/vault?secret=JBSWY3DPEHPK3PXP").is_ok());
+        assert!(validate_totp("JBSW Y3DP EHPK 3PXP").is_ok());
+        assert!(validate_totp("JBSWY3DP1").is_err());
+    }
+
+    #[test]
+    fn checks_ssh_keys() {
+        let (private_key, public_key) = new_key();
+        assert!(validate_ssh_private_key(&private_key).is_ok());
+        assert!(validate_ssh_public_key(&public_key).is_ok());
+        assert!(validate_ssh_private_key(&public_key).is_err());
+        assert!(validate_ssh_public_key("ssh-ed25519 broken").is_err());
+    }
+
+    #[test]
+    fn finds_fields_by_their_key_or_generic_name() {
+        let card = EntryType::PaymentCard;
+        assert_eq!(card.field_by_key("cvv").unwrap().label, "CVV");
+        assert_eq!(card.field_by_key("value").unwrap().key, "number");
+        assert_eq!(card.field_by_key("name").unwrap().key, "cardholder");
+        assert!(card.field_by_key("password").is_err());
+        assert!(EntryType::SecureNote.field_by_key("url").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_values_when_setting_fields() {
+        let mut entry = entry(EntryType::PaymentCard);
+        let number = entry.entry_type.field_by_key("number").unwrap();
+        let (valid, invalid) = ("4111 1111 1111 1111", "4111 1111 1111 1112");
+        assert!(entry.set_field(number, invalid.to_owned()).is_err());
+        assert_eq!(entry.value, "");
+        entry.set_field(number, valid.to_owned()).unwrap();
+        assert_eq!(entry.value, valid);
+    }
+}
@@ -0,0 +1,48 @@
+use anyhow::Ok;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+use crate::database::merge::{merge_databases, newest_side, MergeReport};
+use crate::database::operations::Database;
+use crate::database::structures::{DatabaseFile, FileState};
+
+impl FileState {
+    pub fn read(path: &Path) -> Result<FileState, anyhow::Error> {
+        let content = fs::read(path)?;
+        Ok(FileState {
+            hash: Sha256::digest(&content).to_vec(),
+            modified: fs::metadata(path)?.modified().ok(),
+        })
+    }
+}
+
+/// True if another program wrote the file since the vault was loaded or saved last.
+/// A vault which was deleted meanwhile is simply written again
+pub fn changed_on_disk(db: &DatabaseFile, path: &Path) -> bool {
+    match (&db.file_state, FileState::read(path)) {
+        (Some(loaded), std::result::Result::Ok(current)) => loaded.hash != current.hash,
+        _ => false,
+    }
+}
+
+/// Merges the vault on disk into `db`, `base` is the vault like it was loaded or saved last.
+/// Conflicts keep the entry changed last
+pub fn merge_from_disk(
+    db: &mut DatabaseFile,
+    path: &Path,
+    base: &DatabaseFile,
+) -> Result<MergeReport, anyhow::Error> {
+    let other = DatabaseFile::load(path, &db.password)?;
+    let (mut merged, report) = merge_databases(db, &other, Some(base), |x| Ok(newest_side(x)))?;
+    merged.file_state = db.file_state.take();
+    *db = merged;
+    Ok(report)
+}
+
+/// Saves the vault and remembers the new state of the file
+pub fn save_to(db: &mut DatabaseFile, path: &Path) -> Result<(), anyhow::Error> {
+    db.save_database(&Some(path.to_path_buf()))?;
+    db.file_state = Some(FileState::read(path)?);
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use anyhow::{anyhow, Ok};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::utils::host::host_name;
+use crate::utils::terminal_interactions::prompt_user;
+
+/// Process which has the vault open, stored as JSON in the lock file
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub host: String,
+    pub since: DateTime<Local>,
+}
+
+impl fmt::Display for LockHolder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "vault is open in PID {} on host {} since {}",
+            self.pid,
+            self.host,
+            self.since.format("%Y-%m-%d %H:%M:%S")
+        )
+    }
+}
+
+impl LockHolder {
+    /// Only processes of this machine can be checked, locks of other hosts are never stale
+    fn is_stale(&self) -> bool {
+        self.host == host_name() && !process_is_running(self.pid)
+    }
+}
+
+pub enum LockAttempt {
+    Acquired(VaultLock),
+    Held(LockHolder),
+}
+
+/// Advisory lock of an open vault, the lock file is removed again on drop
+pub struct VaultLock {
+    path: PathBuf,
+}
+
+impl VaultLock {
+    /// Creates `.<file_name>.lock` next to the vault, locks of crashed processes are replaced
+    pub fn acquire(vault: &Path) -> Result<LockAttempt, anyhow::Error> {
+        let path = lock_path(vault)?;
+        let holder = LockHolder {
+            pid: std::process::id(),
+            host: host_name(),
+            since: Local::now(),
+        };
+        // the lock is written completely before it is linked into place, so readers never see half of it
+        let temporary = path.with_extension(format!("lock.{}", holder.pid));
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temporary)?;
+        file.write_all(serde_json::to_string(&holder)?.as_bytes())?;
+        drop(file);
+
+        let result = loop {
+            match fs::hard_link(&temporary, &path) {
+                std::result::Result::Ok(()) => break Ok(LockAttempt::Acquired(VaultLock { path })),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+                Err(err) => break Err(err.into()),
+            }
+
+            // locks are linked into place completely written, an unreadable one wasn't created by a vault
+            let content = match fs::read_to_string(&path) {
+                std::result::Result::Ok(content) => content,
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => break Err(err.into()),
+            };
+            match serde_json::from_str::<LockHolder>(&content).ok() {
+                Some(holder) if !holder.is_stale() => break Ok(LockAttempt::Held(holder)),
+                Some(holder) => println!("Removing the stale lock of PID {}", holder.pid),
+                None => println!("Removing the unreadable lock file {}", path.display()),
+            }
+            if let Err(err) = remove_if_unchanged(&path, &content, holder.pid) {
+                break Err(err);
+            }
+        };
+        let _ = fs::remove_file(&temporary);
+        result
+    }
+
+    /// Locks the vault for commands which write it, they refuse to run while it is open somewhere else
+    pub fn acquire_for_writing(vault: &Path) -> Result<VaultLock, anyhow::Error> {
+        match VaultLock::acquire(vault)? {
+            LockAttempt::Acquired(lock) => Ok(lock),
+            LockAttempt::Held(holder) => Err(anyhow!("{}, close it first", holder)),
+        }
+    }
+
+    /// Locks the vault for `open`. If another process holds the lock the user can open it read-only
+    /// (None), break the lock, e.g. of a crashed process on another host, or abort
+    pub fn acquire_or_ask(vault: &Path) -> Result<Option<VaultLock>, anyhow::Error> {
+        let holder = match VaultLock::acquire(vault)? {
+            LockAttempt::Acquired(lock) => return Ok(Some(lock)),
+            LockAttempt::Held(holder) => holder,
+        };
+        println!("{}", holder);
+        match prompt_user("Open it read-only, break the lock or abort? [r/b/A] ")?
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "r" => Ok(None),
+            "b" => {
+                match fs::remove_file(lock_path(vault)?) {
+                    Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+                    _ => {}
+                }
+                match VaultLock::acquire(vault)? {
+                    LockAttempt::Acquired(lock) => Ok(Some(lock)),
+                    LockAttempt::Held(holder) => Err(anyhow!("{}", holder)),
+                }
+            }
+            _ => Err(anyhow!("{}", holder)),
+        }
+    }
+}
+
+impl Drop for VaultLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Removes the lock file if it still has `content`. It is moved away first, so a process which took
+/// over the same stale lock a moment earlier doesn't lose its new lock
+fn remove_if_unchanged(path: &Path, content: &str, pid: u32) -> Result<(), anyhow::Error> {
+    let moved = path.with_extension(format!("lock.stale.{}", pid));
+    match fs::rename(path, &moved) {
+        std::result::Result::Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    }
+    if fs::read_to_string(&moved).ok().as_deref() != Some(content) {
+        // the lock of another process, it is put back unless a third one was faster
+        let _ = fs::hard_link(&moved, path);
+    }
+    fs::remove_file(&moved)?;
+    Ok(())
+}
+
+fn lock_path(vault: &Path) -> Result<PathBuf, anyhow::Error> {
+    let file_name = vault
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file", vault.display()))?;
+    Ok(vault.with_file_name(format!(".{}.lock", file_name.to_string_lossy())))
+}
+
+#[cfg(unix)]
+fn process_is_running(pid: u32) -> bool {
+    // signal 0 only checks if the process exists, EPERM means it exists but belongs to another user
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn process_is_running(_pid: u32) -> bool {
+    true
+}
@@ -0,0 +1,187 @@
+use chrono::{DateTime, FixedOffset};
+use std::collections::HashSet;
+
+use super::structures::{DatabaseFile, PasswordEntry, RecycledEntry, Tombstone};
+
+/// State of one entry in a vault
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntryState<'a> {
+    Active(&'a PasswordEntry),
+    Recycled(&'a RecycledEntry),
+    Purged(&'a Tombstone),
+}
+
+impl EntryState<'_> {
+    /// Time of the last change, used to pick the newer side of a conflict
+    pub fn changed_at(&self) -> Option<DateTime<FixedOffset>> {
+        match self {
+            EntryState::Active(entry) => entry.last_modified_at(),
+            EntryState::Recycled(recycled) => Some(recycled.deleted_at.fixed_offset()),
+            EntryState::Purged(tombstone) => Some(tombstone.deleted_at.fixed_offset()),
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        match self {
+            EntryState::Active(entry) => &entry.title,
+            EntryState::Recycled(recycled) => &recycled.entry.title,
+            EntryState::Purged(_) => "",
+        }
+    }
+
+    /// Purged entries are equal regardless of when they were purged
+    fn same_as(&self, other: &EntryState) -> bool {
+        match (self, other) {
+            (EntryState::Purged(_), EntryState::Purged(_)) => true,
+            _ => self == other,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Side {
+    A,
+    B,
+}
+
+/// Both vaults changed the entry differently since the base
+pub struct Conflict<'a> {
+    pub id: &'a str,
+    pub a: Option<EntryState<'a>>,
+    pub b: Option<EntryState<'a>>,
+}
+
+#[derive(Default)]
+pub struct MergeReport {
+    pub from_a: usize,
+    pub from_b: usize,
+    pub unchanged: usize,
+    pub conflicts: usize,
+}
+
+impl PasswordEntry {
+    /// `last_modified` is stored in the display format of chrono
+    pub fn last_modified_at(&self) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_str(&self.last_modified, "%Y-%m-%d %H:%M:%S%.f %:z").ok()
+    }
+}
+
+impl DatabaseFile {
+    /// Compares the entries and the config, every save encrypts with a new nonce so the files
+    /// can't be compared
+    pub fn same_content(&self, other: &DatabaseFile) -> bool {
+        self.config == other.config
+            && self.entries == other.entries
+            && self.recycle_bin == other.recycle_bin
+            && self.tombstones == other.tombstones
+    }
+
+    fn entry_state(&self, id: &str) -> Option<EntryState<'_>> {
+        if let Some(entry) = self.entries.iter().find(|x| x.id == id) {
+            return Some(EntryState::Active(entry));
+        }
+        if let Some(recycled) = self.recycle_bin.iter().find(|x| x.entry.id == id) {
+            return Some(EntryState::Recycled(recycled));
+        }
+        self.tombstones
+            .iter()
+            .filter(|x| x.id == id)
+            .max_by_key(|x| x.deleted_at)
+            .map(EntryState::Purged)
+    }
+
+    /// Ids of all entries in the order of the vault, including recycled and purged ones
+    fn entry_ids(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .map(|x| x.id.as_str())
+            .chain(self.recycle_bin.iter().map(|x| x.entry.id.as_str()))
+            .chain(self.tombstones.iter().map(|x| x.id.as_str()))
+            .collect()
+    }
+}
+
+/// The side which was changed last, entries without a valid time lose
+pub fn newest_side(conflict: &Conflict) -> Side {
+    let a = conflict.a.and_then(|x| x.changed_at());
+    let b = conflict.b.and_then(|x| x.changed_at());
+    match (conflict.a, conflict.b) {
+        (None, _) => Side::B,
+        (_, None) => Side::A,
+        _ if b > a => Side::B,
+        _ => Side::A,
+    }
+}
+
+/// Merges the entries of `b` into `a` by their id. With the common `base` only entries changed
+/// on both sides are conflicts, without it every difference is one. Conflicts are decided by
+/// `resolve`. The config and password of `a` are kept
+pub fn merge_databases<F>(
+    a: &DatabaseFile,
+    b: &DatabaseFile,
+    base: Option<&DatabaseFile>,
+    mut resolve: F,
+) -> Result<(DatabaseFile, MergeReport), anyhow::Error>
+where
+    F: FnMut(&Conflict) -> Result<Side, anyhow::Error>,
+{
+    let mut merged = a.clone();
+    merged.entries.clear();
+    merged.recycle_bin.clear();
+    merged.tombstones.clear();
+    let mut report = MergeReport::default();
+
+    let mut seen = HashSet::new();
+    for id in a.entry_ids().into_iter().chain(b.entry_ids()) {
+        if !seen.insert(id) {
+            continue;
+        }
+        let state_a = a.entry_state(id);
+        let state_b = b.entry_state(id);
+        let state_base = base.and_then(|x| x.entry_state(id));
+        let same = |x: Option<EntryState>, y: Option<EntryState>| match (x, y) {
+            (Some(x), Some(y)) => x.same_as(&y),
+            (None, None) => true,
+            _ => false,
+        };
+
+        let side = if same(state_a, state_b) {
+            report.unchanged += 1;
+            Side::A
+        } else if base.is_some() && same(state_a, state_base) {
+            report.from_b += 1;
+            Side::B
+        } else if base.is_some() && same(state_b, state_base) {
+            report.from_a += 1;
+            Side::A
+        } else if base.is_none() && (state_a.is_none() || state_b.is_none()) {
+            // without a base a missing entry is new on the other side
+            if state_a.is_some() {
+                report.from_a += 1;
+                Side::A
+            } else {
+                report.from_b += 1;
+                Side::B
+            }
+        } else {
+            report.conflicts += 1;
+            resolve(&Conflict {
+                id,
+                a: state_a,
+                b: state_b,
+            })?
+        };
+
+        let state = match side {
+            Side::A => state_a,
+            Side::B => state_b,
+        };
+        match state {
+            Some(EntryState::Active(entry)) => merged.entries.push(entry.clone()),
+            Some(EntryState::Recycled(recycled)) => merged.recycle_bin.push(recycled.clone()),
+            Some(EntryState::Purged(tombstone)) => merged.tombstones.push(tombstone.clone()),
+            None => {}
+        }
+    }
+    Ok((merged, report))
+}
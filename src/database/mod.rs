@@ -1,2 +1,6 @@
+pub mod fields;
+pub mod file_state;
+pub mod lock;
+pub mod merge;
 pub mod operations;
 pub mod structures;
\ No newline at end of file
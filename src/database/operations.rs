@@ -1,11 +1,17 @@
-use anyhow::Ok;
-use chrono::Local;
+use anyhow::{anyhow, Ok};
+use chrono::{Duration, Local};
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
-use crate::database::structures::{Config, DatabaseFile, EntryType};
+use crate::database::structures::{
+    default_recycle_bin_retention_days, Config, DatabaseFile, EntryType, FileState, RecycledEntry,
+    Tombstone,
+};
 use crate::encryption_and_decryption::argon::{decrypt_text, encrypt_text};
+use crate::utils::registry::{remember_vault, Registry};
+use crate::utils::settings::settings;
 use crate::utils::terminal_interactions::{prompt_password, prompt_user};
 use crate::New;
 
@@ -13,12 +19,22 @@ use super::structures::PasswordEntry;
 
 pub trait Database {
     fn new(path: &mut Option<PathBuf>, database_name: &str) -> Result<Box<Self>, anyhow::Error>;
+    fn load(path: &Path, password: &str) -> Result<Box<Self>, anyhow::Error>;
     fn add_empty_entry(&mut self) -> String;
     fn remove_entry_by_id(&mut self, id: String) -> bool;
+    fn restore_entry_by_id(&mut self, id: String) -> bool;
+    fn purge_entry_by_id(&mut self, id: String) -> bool;
+    fn purge_expired_entries(&mut self) -> usize;
     fn save_database(&self, path: &Option<PathBuf>) -> Result<(), anyhow::Error>;
     fn get_value_from_selected_detail(&self, index_detail: usize, id: String) -> String;
-    fn update_entry(&mut self, index_detail: usize, id: String, message: Vec<String>);
+    fn update_entry(
+        &mut self,
+        index_detail: usize,
+        id: String,
+        message: Vec<String>,
+    ) -> Result<(), anyhow::Error>;
     fn get_entry_by_id(&self, id: String) -> Option<&PasswordEntry>;
+    fn find_entry(&self, query: &str) -> Result<&PasswordEntry, anyhow::Error>;
 }
 
 impl Database for DatabaseFile {
@@ -27,17 +43,26 @@ impl Database for DatabaseFile {
         database_name: &str,
     ) -> Result<Box<DatabaseFile>, anyhow::Error> {
         if path.is_none() {
-            *path = Some(env::current_dir()?.join(database_name));
+            *path = Some(resolve_database_path(path, database_name)?);
         }
 
         let password = rpassword::prompt_password("Please enter the password:")?;
-        let text = decrypt_text(
+        DatabaseFile::load(
             path.as_ref()
                 .unwrap_or(&PathBuf::new().join(database_name.to_owned() + ".vault")),
             &password,
-        )?;
+        )
+    }
+
+    /// Decrypts the database without asking for the password
+    fn load(path: &Path, password: &str) -> Result<Box<DatabaseFile>, anyhow::Error> {
+        // read before decrypting, a change in between is noticed on save instead of getting lost
+        let file_state = FileState::read(path)?;
+        let (text, kdf) = decrypt_text(&path.to_path_buf(), password)?;
         let mut db: DatabaseFile = serde_json::from_str(&text)?;
         db.last_access = Local::now();
+        db.file_state = Some(file_state);
+        db.kdf = kdf;
 
         Ok(Box::new(db))
     }
@@ -60,65 +85,173 @@ impl Database for DatabaseFile {
             entry_type: EntryType::ClassicPassword,
             last_modified: Local::now().to_string(),
             files: Vec::new(),
+            extra_fields: Vec::new(),
+            group: String::new(),
         });
         id
     }
 
+    /// Moves the entry into the recycle bin, returns false if no entry has the given id
     fn remove_entry_by_id(&mut self, id: String) -> bool {
-        //TODO change function calls or design
-        let count_before = self.entries.len();
-        self.entries = self
-            .entries
-            .iter()
-            .filter(|x| x.id != id)
-            .cloned()
-            .collect();
-        count_before < self.entries.len()
+        let position = match self.entries.iter().position(|x| x.id == id) {
+            Some(position) => position,
+            None => return false,
+        };
+        let entry = self.entries.remove(position);
+        self.recycle_bin.push(RecycledEntry {
+            entry,
+            deleted_at: Local::now(),
+        });
+        true
+    }
+
+    fn restore_entry_by_id(&mut self, id: String) -> bool {
+        let position = match self.recycle_bin.iter().position(|x| x.entry.id == id) {
+            Some(position) => position,
+            None => return false,
+        };
+        let mut recycled = self.recycle_bin.remove(position);
+        // restoring counts as a change, so merges prefer it over the older deletion
+        recycled.entry.last_modified = Local::now().to_string();
+        self.entries.push(recycled.entry);
+        true
+    }
+
+    /// Deletes the entry from the recycle bin for good
+    fn purge_entry_by_id(&mut self, id: String) -> bool {
+        let count_before = self.recycle_bin.len();
+        self.recycle_bin.retain(|x| x.entry.id != id);
+        if count_before == self.recycle_bin.len() {
+            return false;
+        }
+        self.tombstones.push(Tombstone {
+            id,
+            deleted_at: Local::now(),
+        });
+        true
+    }
+
+    /// Purges all recycle bin entries older than the configured retention, returns the amount removed
+    fn purge_expired_entries(&mut self) -> usize {
+        let retention_days = self.config.recycle_bin_retention_days;
+        if retention_days == 0 {
+            return 0;
+        }
+        let deadline = Local::now() - Duration::days(retention_days.into());
+        let (kept, expired): (Vec<_>, Vec<_>) = self
+            .recycle_bin
+            .drain(..)
+            .partition(|x| x.deleted_at > deadline);
+        self.recycle_bin = kept;
+        for recycled in expired.iter() {
+            self.tombstones.push(Tombstone {
+                id: recycled.entry.id.clone(),
+                deleted_at: Local::now(),
+            });
+        }
+        expired.len()
     }
 
     fn save_database(&self, path: &Option<PathBuf>) -> Result<(), anyhow::Error> {
+        let path = path
+            .as_ref()
+            .ok_or_else(|| anyhow!("there is no file to save the vault to"))?;
+        rotate_backups(path, settings().backup_count)?;
         let serialized_db = serde_json::to_string(self)?;
-        encrypt_text(
-            &serialized_db,
-            path.as_ref().unwrap(),
-            self.password.as_str(),
-        )?;
+        encrypt_text(&serialized_db, path, self.password.as_str(), &self.kdf)?;
         Ok(())
     }
 
     fn get_value_from_selected_detail(&self, index_detail: usize, id: String) -> String {
-        let entry = self.entries.iter().find(|x| x.id == id).unwrap();
-        match index_detail {
-            0 => entry.title.clone(),
-            1 => entry.name.clone(),
-            2 => entry.value.clone(),
-            3 => entry.url.clone(),
-            4 => entry.comment.clone(),
-            _ => String::from(""),
+        let entry = match self.entries.iter().find(|x| x.id == id) {
+            Some(entry) => entry,
+            None => return String::new(),
+        };
+        match entry.fields().get(index_detail) {
+            Some(spec) => entry.get_field(spec),
+            None => String::from(""),
         }
     }
 
-    fn update_entry(&mut self, index_detail: usize, id: String, message: Vec<String>) {
+    fn update_entry(
+        &mut self,
+        index_detail: usize,
+        id: String,
+        message: Vec<String>,
+    ) -> Result<(), anyhow::Error> {
         let converted_message = message
             .last()
             .unwrap_or(&String::from("error while parsing text"))
             .to_owned();
 
-        let entry = self.entries.iter_mut().find(|x| x.id == id).unwrap();
-        match index_detail {
-            0 => entry.title = converted_message,
-            1 => entry.name = converted_message,
-            2 => entry.value = converted_message,
-            3 => entry.url = converted_message,
-            4 => entry.comment = converted_message,
-            _ => {}
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|x| x.id == id)
+            .ok_or_else(|| anyhow!("the entry doesn't exist anymore"))?;
+        if let Some(spec) = entry.fields().get(index_detail) {
+            entry.set_field(spec, converted_message)?;
         }
         entry.last_modified = Local::now().to_string();
+        Ok(())
     }
 
     fn get_entry_by_id(&self, id: String) -> Option<&PasswordEntry> {
         self.entries.iter().find(|x| x.id == id)
     }
+
+    /// Finds an entry by its id or its title, fails if the title is not unique
+    fn find_entry(&self, query: &str) -> Result<&PasswordEntry, anyhow::Error> {
+        if let Some(entry) = self.get_entry_by_id(query.to_owned()) {
+            return Ok(entry);
+        }
+        let mut matches = self.entries.iter().filter(|x| x.title == query);
+        match (matches.next(), matches.next()) {
+            (Some(entry), None) => Ok(entry),
+            (Some(_), Some(_)) => Err(anyhow!(
+                "multiple entries are called '{}', please use the id instead",
+                query
+            )),
+            _ => Err(anyhow!("no entry with the title or id '{}' found", query)),
+        }
+    }
+}
+
+/// Keeps the last `count` versions of the file as `<file>.bak1` (newest) to `<file>.bak<count>`
+fn rotate_backups(path: &Path, count: u32) -> Result<(), anyhow::Error> {
+    if count == 0 || !path.exists() {
+        return Ok(());
+    }
+    let backup = |number: u32| PathBuf::from(format!("{}.bak{}", path.display(), number));
+    for number in (1..count).rev() {
+        if backup(number).exists() {
+            fs::rename(backup(number), backup(number + 1))?;
+        }
+    }
+    fs::copy(path, backup(1))?;
+    Ok(())
+}
+
+/// Without an explicit path the database name is looked up in the current directory,
+/// if there is no such file it can be the alias of a known vault
+pub fn resolve_database_path(
+    path: &Option<PathBuf>,
+    database_name: &str,
+) -> Result<PathBuf, anyhow::Error> {
+    if let Some(path) = path {
+        return Ok(path.clone());
+    }
+    let path = env::current_dir()?.join(database_name);
+    if !path.exists() {
+        if let Some(known) = Registry::load()
+            .ok()
+            .as_ref()
+            .and_then(|x| x.find_alias(database_name))
+        {
+            return Ok(known.path.clone());
+        }
+    }
+    Ok(path)
 }
 
 pub fn create_new_database(mut args: New) -> Result<(), anyhow::Error> {
@@ -126,15 +259,26 @@ pub fn create_new_database(mut args: New) -> Result<(), anyhow::Error> {
         args.path = Some(env::current_dir()?.join(args.database_name.to_owned() + ".vault"));
     }
 
-    let password = prompt_password()?;
-    let author = prompt_user("Please enter your name:");
-    let comment = prompt_user("Please enter a description for the database:");
+    let password = prompt_password("the password")?;
+    let author = prompt_user("Please enter your name:")?;
+    let comment = prompt_user("Please enter a description for the database:")?;
 
     let db = DatabaseFile {
         entries: Vec::new(),
-        config: Config { comment, author },
+        recycle_bin: Vec::new(),
+        tombstones: Vec::new(),
+        config: Config {
+            comment,
+            author,
+            recycle_bin_retention_days: args
+                .recycle_bin_days
+                .unwrap_or_else(default_recycle_bin_retention_days),
+            sync_target: None,
+        },
         last_access: Local::now(),
         password: password.clone(),
+        file_state: None,
+        kdf: settings().kdf,
     };
 
     let serialized_db = serde_json::to_string(&db)?;
@@ -144,6 +288,10 @@ pub fn create_new_database(mut args: New) -> Result<(), anyhow::Error> {
             .as_ref()
             .unwrap_or(&PathBuf::new().join(String::from(&args.database_name) + ".vault")),
         &password,
+        &db.kdf,
     )?;
+    if let Some(path) = &args.path {
+        remember_vault(path);
+    }
     Ok(())
 }
@@ -1,14 +1,22 @@
 use serde::{Serialize, Deserialize};
 use chrono::{DateTime, Local};
+use std::path::PathBuf;
+use std::time::SystemTime;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
-#[derive(Serialize, Deserialize, Clone, Debug, Zeroize, ZeroizeOnDrop)]
+use crate::encryption_and_decryption::argon::KdfParams;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Zeroize, ZeroizeOnDrop)]
 pub enum EntryType{
     ClassicPassword,
-    EnvironmentVariable
+    EnvironmentVariable,
+    SshKey,
+    PaymentCard,
+    SecureNote,
+    Identity
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Zeroize, ZeroizeOnDrop)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Zeroize, ZeroizeOnDrop)]
 pub struct PasswordEntry {
     pub id: String,
     pub title: String,
@@ -18,27 +26,85 @@ pub struct PasswordEntry {
     pub comment: String,
     pub entry_type: EntryType,
     pub last_modified: String,
-    pub files: Vec<CustomFile>
+    pub files: Vec<CustomFile>,
+    #[serde(default)]
+    pub extra_fields: Vec<ExtraField>, // fields only some entry types have, see fields.rs
+    #[serde(default)]
+    pub group: String // folder path like `Work/Servers`, kept from imports
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Zeroize, ZeroizeOnDrop)]
+pub struct ExtraField {
+    pub key: String,
+    pub value: String
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Zeroize, ZeroizeOnDrop)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Zeroize, ZeroizeOnDrop)]
 pub struct CustomFile {
     pub content: String, //Base64 encoding
     pub comment: String,
     pub name: String
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecycledEntry {
+    pub entry: PasswordEntry,
+    pub deleted_at: DateTime<Local>
+}
+
+/// Remembers purged entries, so merging with an older copy of the vault doesn't bring them back
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Tombstone {
+    pub id: String,
+    pub deleted_at: DateTime<Local>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Config{
     // maybe all the argon props and more
     pub(crate) comment: String,
     pub(crate) author: String,
+    /// Days after which entries in the recycle bin are purged, 0 keeps them forever
+    #[serde(default = "default_recycle_bin_retention_days")]
+    pub(crate) recycle_bin_retention_days: u32,
+    /// Where `vault sync` pushes the vault to and pulls it from
+    #[serde(default)]
+    pub(crate) sync_target: Option<SyncTarget>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum SyncTarget {
+    /// Local or mounted directory, e.g. a network share
+    Directory(PathBuf),
+    /// Url of a git repository, the encrypted vault is committed to it
+    Git(String),
+}
+
+pub fn default_recycle_bin_retention_days() -> u32 {
+    30
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct DatabaseFile{
     pub(crate) entries: Vec<PasswordEntry>,
+    #[serde(default)]
+    pub(crate) recycle_bin: Vec<RecycledEntry>,
+    #[serde(default)]
+    pub(crate) tombstones: Vec<Tombstone>,
     pub(crate) config: Config,
     pub(crate) last_access: DateTime<Local>,
-    pub(crate) password: String
+    pub(crate) password: String,
+    /// State of the file when the vault was loaded, it is never written into the vault
+    #[serde(skip)]
+    pub(crate) file_state: Option<FileState>,
+    /// Stored in the header of the file instead of the encrypted part
+    #[serde(skip)]
+    pub(crate) kdf: KdfParams,
+}
+
+/// Fingerprint of the vault file, every save changes it because of the new nonce
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileState {
+    pub(crate) hash: Vec<u8>,
+    pub(crate) modified: Option<SystemTime>,
 }
@@ -4,15 +4,18 @@ use chacha20poly1305::{
     XChaCha20Poly1305,
 };
 use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
 use std::str;
 use std::{
     fs::File,
-    io::{Read, Write},
-    path::PathBuf,
+    io::Read,
+    path::{Path, PathBuf},
     vec,
 };
 use zeroize::Zeroize;
 
+use crate::utils::atomic_file::write_atomically;
+
 macro_rules! empty_all {
     ($($item:expr), *) => {
         $(
@@ -20,59 +23,139 @@ macro_rules! empty_all {
         )*
     }
 }
+/// Starts files which store their KDF parameters, files without it use the defaults
+const HEADER_MAGIC: &[u8; 4] = b"VLT2";
+
+/// Argon2id parameters of a vault file
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct KdfParams {
+    /// Memory in KiB
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
 // Orientation: https://cheatsheetseries.owasp.org/cheatsheets/Password_Storage_Cheat_Sheet.html
-fn argon2_config<'a>() -> argon2::Config<'a> {
+impl Default for KdfParams {
+    fn default() -> KdfParams {
+        KdfParams {
+            memory_kib: 16 * 1024,
+            iterations: 8,
+            parallelism: 8,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Checks the bounds of the parameters, returns the invalid one and why. Files are checked as well because
+    /// their header isn't authenticated and a huge memory cost would abort the process
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        if !(1..=255).contains(&self.parallelism) {
+            return Err(("parallelism", String::from("must be between 1 and 255")));
+        }
+        if self.iterations == 0 {
+            return Err(("iterations", String::from("must be at least 1")));
+        }
+        if self.memory_kib < 8 * self.parallelism {
+            return Err((
+                "memory_kib",
+                format!(
+                    "must be at least 8 times kdf.parallelism ({})",
+                    8 * self.parallelism
+                ),
+            ));
+        }
+        if self.memory_kib > 4 * 1024 * 1024 {
+            return Err((
+                "memory_kib",
+                String::from("must be at most 4 GiB (4194304)"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn argon2_config<'a>(kdf: &KdfParams) -> argon2::Config<'a> {
     argon2::Config {
         variant: argon2::Variant::Argon2id,
         hash_length: 32,
-        lanes: 8,
-        mem_cost: 16 * 1024,
-        time_cost: 8,
+        lanes: kdf.parallelism,
+        mem_cost: kdf.memory_kib,
+        time_cost: kdf.iterations,
         ..Default::default()
     }
 }
 
 pub fn encrypt_text(
     text: &str,
-    dist_file_path: &PathBuf,
+    dist_file_path: &Path,
     password: &str,
+    kdf: &KdfParams,
 ) -> Result<(), anyhow::Error> {
     let mut salt = [0u8; 32];
     let mut nonce = [0u8; 19];
     OsRng.fill_bytes(&mut salt);
     OsRng.fill_bytes(&mut nonce);
 
-    let mut key = argon2::hash_raw(password.as_bytes(), &salt, &argon2_config())?;
+    let mut key = argon2::hash_raw(password.as_bytes(), &salt, &argon2_config(kdf))?;
 
     // [..32] skips the salt
-    let aead = XChaCha20Poly1305::new(key[..32].as_ref().into());
+    let aead = XChaCha20Poly1305::new_from_slice(&key[..32])
+        .map_err(|err| anyhow!("Creating cipher: {}", err))?;
     let mut stream_encryptor = stream::EncryptorBE32::from_aead(aead, nonce.as_ref().into());
-    let mut dist_file = File::create(dist_file_path)?;
-
-    dist_file.write_all(&salt)?;
-    dist_file.write_all(&nonce)?;
+    let mut content = HEADER_MAGIC.to_vec();
+    for value in [kdf.memory_kib, kdf.iterations, kdf.parallelism] {
+        content.extend_from_slice(&value.to_le_bytes());
+    }
+    content.extend_from_slice(&salt);
+    content.extend_from_slice(&nonce);
 
     let ciphertext = stream_encryptor
         .encrypt_next(text.as_bytes())
         .map_err(|err| anyhow!("Encrypting file: {}", err))?;
-    dist_file.write_all(&ciphertext)?;
+    content.extend_from_slice(&ciphertext);
+    // the vault is replaced as a whole, a failed save never truncates it
+    write_atomically(dist_file_path, &content)?;
 
     empty_all!(nonce, key, salt);
 
     Ok(())
 }
 
+/// Returns the text and the KDF parameters of the file, so it can be written with them again
 pub fn decrypt_text(
     encrypted_file_path: &PathBuf,
     password: &str,
-) -> Result<String, anyhow::Error> {
+) -> Result<(String, KdfParams), anyhow::Error> {
     let mut salt = [0u8; 32];
     let mut nonce = [0u8; 19];
 
     let mut encrypted_file = File::open(encrypted_file_path)?;
     let mut file_content = String::new();
 
-    let mut read_count = encrypted_file.read(&mut salt)?;
+    let mut read_count = encrypted_file.read(&mut salt[..HEADER_MAGIC.len()])?;
+    let mut kdf = KdfParams::default();
+    if read_count == HEADER_MAGIC.len() && &salt[..HEADER_MAGIC.len()] == HEADER_MAGIC {
+        let mut values = [0u8; 12];
+        encrypted_file
+            .read_exact(&mut values)
+            .map_err(|_| anyhow!("Error reading KDF parameters."))?;
+        let value = |index: usize| {
+            let bytes = &values[index * 4..index * 4 + 4];
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        };
+        kdf = KdfParams {
+            memory_kib: value(0),
+            iterations: value(1),
+            parallelism: value(2),
+        };
+        kdf.validate()
+            .map_err(|_| anyhow!("Error reading KDF parameters."))?;
+        read_count = 0;
+    }
+    // files without the header start with the salt right away
+    read_count += encrypted_file.read(&mut salt[read_count..])?;
     if read_count != salt.len() {
         return Err(anyhow!("Error reading salt."));
     }
@@ -82,9 +165,10 @@ pub fn decrypt_text(
         return Err(anyhow!("Error reading nonce."));
     }
 
-    let mut key = argon2::hash_raw(password.as_bytes(), &salt, &argon2_config())?;
+    let mut key = argon2::hash_raw(password.as_bytes(), &salt, &argon2_config(&kdf))?;
 
-    let aead = XChaCha20Poly1305::new(key[..32].as_ref().into());
+    let aead = XChaCha20Poly1305::new_from_slice(&key[..32])
+        .map_err(|err| anyhow!("Creating cipher: {}", err))?;
     let mut stream_decryptor = stream::DecryptorBE32::from_aead(aead, nonce.as_ref().into());
 
     let mut buf: Vec<u8> = vec![];
@@ -95,5 +179,70 @@ pub fn decrypt_text(
     file_content.push_str(str::from_utf8(&text)?);
 
     empty_all!(nonce, key, salt);
-    Ok(file_content)
+    Ok((file_content, kdf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("vault-argon-{}-{}", process::id(), name))
+    }
+
+    #[test]
+    fn reads_files_without_header_with_default_params() {
+        let path = temp_path("legacy");
+        // the format before the header: salt || nonce || ciphertext
+        let salt = [7u8; 32];
+        let nonce = [3u8; 19];
+        let key = argon2::hash_raw(b"pw", &salt, &argon2_config(&KdfParams::default())).unwrap();
+        let aead = XChaCha20Poly1305::new_from_slice(&key[..32]).unwrap();
+        let ciphertext = stream::EncryptorBE32::from_aead(aead, nonce.as_ref().into())
+            .encrypt_next(b"{\"entries\":[]}".as_ref())
+            .unwrap();
+        fs::write(&path, [&salt[..], &nonce[..], &ciphertext[..]].concat()).unwrap();
+
+        let result = decrypt_text(&path, "pw");
+        fs::remove_file(&path).unwrap();
+        let (text, kdf) = result.unwrap();
+        assert_eq!(text, "{\"entries\":[]}");
+        assert_eq!(kdf, KdfParams::default());
+    }
+
+    #[test]
+    fn keeps_params_of_the_header() {
+        let path = temp_path("header");
+        let kdf = KdfParams {
+            memory_kib: 64,
+            iterations: 2,
+            parallelism: 4,
+        };
+        encrypt_text("secret text", &path, "pw", &kdf).unwrap();
+
+        let result = decrypt_text(&path, "pw");
+        let wrong_password = decrypt_text(&path, "other");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap(), (String::from("secret text"), kdf));
+        assert!(wrong_password.is_err());
+    }
+
+    #[test]
+    fn rejects_params_out_of_bounds_before_hashing() {
+        let path = temp_path("huge");
+        let mut content = HEADER_MAGIC.to_vec();
+        for value in [u32::MAX, 1, 1] {
+            content.extend_from_slice(&value.to_le_bytes());
+        }
+        content.extend_from_slice(&[0u8; 32 + 19 + 16]);
+        fs::write(&path, content).unwrap();
+
+        let result = decrypt_text(&path, "pw");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Error reading KDF parameters."
+        );
+    }
 }
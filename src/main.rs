@@ -1,3 +1,4 @@
+mod commands;
 mod database;
 mod encryption_and_decryption;
 mod ui;
@@ -6,10 +7,16 @@ mod utils;
 use crate::database::structures::DatabaseFile;
 use anyhow::Ok;
 use clap::{Parser, Subcommand};
-use database::operations::{create_new_database, Database};
+use database::file_state::save_to;
+use database::lock::VaultLock;
+use database::operations::{create_new_database, resolve_database_path, Database};
 use std::path::PathBuf;
+use std::time::Duration;
 
 use ui::home_screen::run_gui;
+use ui::structures::Session;
+use utils::registry::remember_vault;
+use utils::settings::{load_settings, settings};
 
 #[derive(Parser)]
 #[clap(
@@ -19,12 +26,39 @@ use ui::home_screen::run_gui;
 struct Command {
     #[clap(subcommand)]
     subcmd: SubCommand,
+
+    /// Config file to use instead of `$XDG_CONFIG_HOME/vault/config.toml`
+    #[clap(long, global = true)]
+    config: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
 enum SubCommand {
     New(New),
     Open(Open),
+    Get(Get),
+    List(List),
+    /// Prints the environment variable entries as shell exports
+    Env(Env),
+    /// Keeps the vault unlocked in the background for get, list and env
+    #[clap(subcommand)]
+    Agent(AgentCommand),
+    /// Git credential helper, use it with `git config credential.helper '!vault git-credential -d <db>'`
+    GitCredential(GitCredential),
+    /// Serves the SSH keys of the vault to ssh over the OpenSSH agent protocol
+    SshAgent(SshAgent),
+    /// Imports the entries of another password manager into the vault
+    Import(Import),
+    /// Exports the entries of the vault for other password managers
+    Export(Export),
+    /// Merges two copies of a vault, e.g. after they were changed on different machines
+    Merge(Merge),
+    /// Pushes and pulls the encrypted vault to a directory or git repository, merging both sides
+    Sync(Sync),
+    /// Lists the vaults which were created or opened before
+    LsVaults(LsVaults),
+    /// Gives a vault a short name which can be used instead of its path
+    Alias(Alias),
 }
 
 /// Simple program to greet a person
@@ -49,10 +83,74 @@ pub struct New {
     /// Absolute path for file is required
     #[clap(short, long)]
     path: Option<PathBuf>,
+
+    /// Days after which deleted entries are purged from the recycle bin (0 keeps them forever)
+    #[clap(long)]
+    recycle_bin_days: Option<u32>,
 }
 
 #[derive(Parser)]
 pub struct Open {
+    /// FileName or alias of the existing database, without it a known vault can be picked
+    #[clap(short, long)]
+    database_name: Option<String>,
+
+    /// Absolute path for file is required
+    #[clap(short, long)]
+    path: Option<PathBuf>,
+
+    /// Changes the days after which deleted entries are purged from the recycle bin
+    #[clap(long)]
+    recycle_bin_days: Option<u32>,
+
+    /// Saves changes automatically every this amount of seconds
+    #[clap(long)]
+    autosave: Option<u64>,
+
+    /// Only shows the entries, nothing can be changed and the file is never written or locked
+    #[clap(long)]
+    read_only: bool,
+
+    /// Draws on the normal screen instead of the alternate one, shown secrets can end up in the scrollback then
+    #[clap(long)]
+    no_alternate_screen: bool,
+}
+
+#[derive(Parser)]
+pub struct Get {
+    /// FileName from the existing database
+    #[clap(short, long)]
+    database_name: String,
+
+    /// Absolute path for file is required
+    #[clap(short, long)]
+    path: Option<PathBuf>,
+
+    /// Title or id of the entry
+    entry: String,
+
+    /// Field to print like username, private_key or cvv, defaults to the password, value or note
+    #[clap(short, long)]
+    field: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct List {
+    /// FileName from the existing database
+    #[clap(short, long)]
+    database_name: String,
+
+    /// Absolute path for file is required
+    #[clap(short, long)]
+    path: Option<PathBuf>,
+
+    /// Also print the field names which can be used with get
+    #[clap(long)]
+    fields: bool,
+}
+
+#[derive(Parser)]
+pub struct Env {
     /// FileName from the existing database
     #[clap(short, long)]
     database_name: String,
@@ -62,16 +160,255 @@ pub struct Open {
     path: Option<PathBuf>,
 }
 
+#[derive(Subcommand)]
+enum AgentCommand {
+    /// Unlocks the vault once and serves it over a unix socket
+    Start(AgentStart),
+    /// Stops the agent of the vault
+    Stop(AgentStop),
+    #[clap(hide = true)]
+    Serve(AgentStart),
+}
+
+#[derive(Parser)]
+pub struct AgentStart {
+    /// FileName from the existing database
+    #[clap(short, long)]
+    database_name: String,
+
+    /// Absolute path for file is required
+    #[clap(short, long)]
+    path: Option<PathBuf>,
+
+    /// Seconds after which the agent stops and the vault has to be unlocked again
+    #[clap(long, default_value_t = 900)]
+    ttl: u64,
+}
+
+#[derive(Parser)]
+pub struct AgentStop {
+    /// FileName from the existing database
+    #[clap(short, long)]
+    database_name: String,
+
+    /// Absolute path for file is required
+    #[clap(short, long)]
+    path: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+pub struct GitCredential {
+    /// FileName from the existing database
+    #[clap(short, long)]
+    database_name: String,
+
+    /// Absolute path for file is required
+    #[clap(short, long)]
+    path: Option<PathBuf>,
+
+    /// Operation requested by git: get, store or erase
+    operation: String,
+}
+
+#[derive(Parser)]
+pub struct SshAgent {
+    /// FileName from the existing database
+    #[clap(short, long)]
+    database_name: String,
+
+    /// Absolute path for file is required
+    #[clap(short, long)]
+    path: Option<PathBuf>,
+
+    /// Unix socket the agent listens on, use it as SSH_AUTH_SOCK
+    #[clap(short, long)]
+    socket: PathBuf,
+
+    /// Ask in the terminal before every use of a key
+    #[clap(long)]
+    confirm: bool,
+
+    /// Stop the agent after this amount of seconds
+    #[clap(long)]
+    lifetime: Option<u64>,
+}
+
+#[derive(Parser)]
+pub struct Import {
+    /// FileName from the existing database
+    #[clap(short, long)]
+    database_name: String,
+
+    /// Absolute path for file is required
+    #[clap(short, long)]
+    path: Option<PathBuf>,
+
+    /// Format of the file to import
+    #[clap(long, value_enum)]
+    from: commands::import::ImportFormat,
+
+    /// File exported by the other password manager
+    file: PathBuf,
+
+    /// Key file of the KeePass database
+    #[clap(long)]
+    keyfile: Option<PathBuf>,
+
+    /// Columns of a CSV file like `title=Name,value=Password`, numbers can be used instead of names
+    #[clap(long)]
+    map: Option<String>,
+
+    /// Only shows the entries which would be created
+    #[clap(long)]
+    dry_run: bool,
+}
+
+#[derive(Parser)]
+pub struct Export {
+    /// FileName from the existing database
+    #[clap(short, long)]
+    database_name: String,
+
+    /// Absolute path for file is required
+    #[clap(short, long)]
+    path: Option<PathBuf>,
+
+    /// Format of the exported file
+    #[clap(long, value_enum)]
+    format: commands::export::ExportFormat,
+
+    /// File the entries are written to
+    output: PathBuf,
+
+    /// Confirms that keepass-xml, csv and json write all passwords as plain text
+    #[clap(long)]
+    unencrypted: bool,
+}
+
+#[derive(Parser)]
+pub struct Merge {
+    /// Vault the changes are merged into
+    a: PathBuf,
+
+    /// Vault with the changes to merge
+    b: PathBuf,
+
+    /// Common ancestor of both vaults, only entries changed in both are conflicts then
+    #[clap(long)]
+    base: Option<PathBuf>,
+
+    /// Where the merged vault is written to, defaults to the first vault
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// Ask for every conflict instead of keeping the entry changed last
+    #[clap(short, long)]
+    interactive: bool,
+}
+
+#[derive(Parser)]
+pub struct Sync {
+    /// FileName from the existing database
+    #[clap(short, long)]
+    database_name: String,
+
+    /// Absolute path for file is required
+    #[clap(short, long)]
+    path: Option<PathBuf>,
+
+    /// Syncs with a local or mounted directory from now on
+    #[clap(long, conflicts_with = "git")]
+    dir: Option<PathBuf>,
+
+    /// Syncs with a git repository from now on, the encrypted vault is committed to it
+    #[clap(long)]
+    git: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct LsVaults {
+    /// Forgets the vaults whose file doesn't exist anymore
+    #[clap(long)]
+    prune: bool,
+}
+
+#[derive(Parser)]
+pub struct Alias {
+    /// Short name of the vault, e.g. `work`
+    alias: String,
+
+    /// FileName from the existing database
+    #[clap(short, long)]
+    database_name: Option<String>,
+
+    /// Absolute path for file is required
+    #[clap(short, long)]
+    path: Option<PathBuf>,
+
+    /// Removes the alias instead
+    #[clap(long, conflicts_with_all = ["database_name", "path"])]
+    remove: bool,
+}
+
 fn main() -> Result<(), anyhow::Error> {
     let command = Command::parse();
+    load_settings(command.config.as_deref())?;
 
     match command.subcmd {
         SubCommand::New(sc) => create_new_database(sc)?,
         SubCommand::Open(mut sc) => {
-            let mut db: DatabaseFile = *Database::new(&mut sc.path, sc.database_name.as_str())?;
-            run_gui(&mut db)?;
-            db.save_database(&sc.path)?;
+            let path = match (&sc.path, &sc.database_name) {
+                (None, None) => match &settings().default_vault {
+                    Some(vault) => resolve_database_path(&None, vault)?,
+                    None => commands::vaults::pick_vault()?,
+                },
+                (path, name) => resolve_database_path(path, name.as_deref().unwrap_or_default())?,
+            };
+            // None when the vault is only read here, e.g. because it is open somewhere else
+            let lock = if sc.read_only {
+                None
+            } else {
+                VaultLock::acquire_or_ask(&path)?
+            };
+            sc.path = Some(path.clone());
+            let mut db: DatabaseFile = *Database::new(&mut sc.path, "")?;
+            remember_vault(&path);
+            if let Some(days) = sc.recycle_bin_days {
+                db.config.recycle_bin_retention_days = days;
+            }
+            let read_only = lock.is_none();
+            if !read_only && (db.purge_expired_entries() > 0 || sc.recycle_bin_days.is_some()) {
+                save_to(&mut db, &path)?;
+            }
+            let mut session = Session::new(path, &db, lock, sc.autosave.map(Duration::from_secs));
+            return run_gui(&mut db, &mut session, !sc.no_alternate_screen);
+        }
+        SubCommand::Get(sc) => return commands::entries::get_entry(sc),
+        SubCommand::List(sc) => return commands::entries::list_entries(sc),
+        SubCommand::Env(sc) => return commands::entries::env_entries(sc),
+        #[cfg(unix)]
+        SubCommand::Agent(AgentCommand::Start(sc)) => return commands::agent::start_agent(sc),
+        #[cfg(unix)]
+        SubCommand::Agent(AgentCommand::Stop(sc)) => return commands::agent::stop_agent(sc),
+        #[cfg(unix)]
+        SubCommand::Agent(AgentCommand::Serve(sc)) => return commands::agent::serve_agent(sc),
+        #[cfg(not(unix))]
+        SubCommand::Agent(_) => {
+            return Err(anyhow::anyhow!("the agent is only supported on unix systems"))
+        }
+        SubCommand::GitCredential(sc) => return commands::git_credential::git_credential(sc),
+        #[cfg(unix)]
+        SubCommand::SshAgent(sc) => return commands::ssh_agent::run_ssh_agent(sc),
+        #[cfg(not(unix))]
+        SubCommand::SshAgent(_) => {
+            return Err(anyhow::anyhow!("the ssh agent is only supported on unix systems"))
         }
+        SubCommand::Import(sc) => return commands::import::import_entries(sc),
+        SubCommand::Export(sc) => return commands::export::export_entries(sc),
+        SubCommand::Merge(sc) => return commands::merge::merge_vaults(sc),
+        SubCommand::Sync(sc) => return commands::sync::sync_vault(sc),
+        SubCommand::LsVaults(sc) => return commands::vaults::list_vaults(sc),
+        SubCommand::Alias(sc) => return commands::vaults::alias_vault(sc),
     }
     clearscreen::clear().expect("failed to clear screen");
     Ok(())
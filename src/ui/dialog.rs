@@ -0,0 +1,72 @@
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use super::enums::PendingAction;
+use super::structures::App;
+
+/// How long a notice stays in the info bar
+const NOTICE_DURATION: Duration = Duration::from_secs(8);
+
+/// Popup in front of everything else, it has to be answered or closed before anything else can be done
+pub enum Dialog {
+    /// Asks before the action is executed
+    Confirm(PendingAction),
+    /// An action failed, any key closes it
+    Error { title: String, message: String },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NoticeLevel {
+    Info,
+    Warning,
+}
+
+/// Short message in the info bar which disappears on its own
+pub struct Notice {
+    pub text: String,
+    pub level: NoticeLevel,
+    pub shown_at: Instant,
+}
+
+impl App {
+    pub fn confirm(&mut self, action: PendingAction) {
+        self.dialog = Some(Dialog::Confirm(action));
+    }
+
+    /// Shows why an action failed instead of crashing with the terminal in raw mode
+    pub fn error(&mut self, title: &str, err: impl Display) {
+        self.dialog = Some(Dialog::Error {
+            title: title.to_owned(),
+            message: err.to_string(),
+        });
+    }
+
+    pub fn notify(&mut self, text: impl Into<String>) {
+        self.notice = Some(Notice {
+            text: text.into(),
+            level: NoticeLevel::Info,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// A notice for something which was refused or needs attention
+    pub fn warn(&mut self, text: impl Into<String>) {
+        self.notice = Some(Notice {
+            text: text.into(),
+            level: NoticeLevel::Warning,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// Removes the notice once it was shown long enough, called on every tick
+    pub fn expire_notice(&mut self) {
+        if self
+            .notice
+            .as_ref()
+            .map(|x| x.shown_at.elapsed() >= NOTICE_DURATION)
+            .unwrap_or(false)
+        {
+            self.notice = None;
+        }
+    }
+}
@@ -1,16 +1,9 @@
-use thiserror::Error;
-use std::io;
-
-#[derive(Error, Debug)]
-pub enum Error {
-    #[error("error reading the DB file: {0}")]
-    ReadDBError(#[from] io::Error),
-    #[error("error parsing the DB file: {0}")]
-    ParseDBError(#[from] serde_json::Error),
-}
+use std::path::PathBuf;
 
 pub enum Event<I> {
     Input(I),
+    /// Text pasted into the terminal
+    Paste(String),
     Tick,
 }
 
@@ -18,10 +11,28 @@ pub enum Event<I> {
 pub enum MenuItem {
     Home,
     PasswordEntries,
-    SelctedEntry
+    SelctedEntry,
+    RecycleBin
 }
 
 pub enum InputMode {
     Navigation,
     Editing,
+    /// Path of the file the vault is saved to instead
+    SaveAs,
+    /// The vault was locked after the lock timeout, the password unlocks it
+    Unlock,
+}
+
+/// Actions which have to be confirmed by the user before they are executed
+pub enum PendingAction {
+    RemoveEntry(String),
+    PurgeEntry(String),
+    /// Switching the type of the entry would hide, reveal or invalidate some of its values
+    ChangeType { id: String, issues: Vec<String> },
+    /// The file was changed by another program, it can be merged, overwritten or saved elsewhere
+    ExternalChange,
+    /// Saving as would replace another file
+    OverwriteFile(PathBuf),
+    Quit,
 }
@@ -1,11 +1,10 @@
-use crossterm::{
-    event::{self, Event as CEvent, KeyCode},
-    terminal::{disable_raw_mode, enable_raw_mode},
-};
+use crossterm::event::{self, Event as CEvent, KeyCode};
 
-use anyhow::Ok;
+use anyhow::{anyhow, Ok};
 use std::io;
-use std::sync::mpsc;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
 use std::time::{Duration, Instant};
 use tui::{
@@ -15,28 +14,55 @@ use tui::{
 };
 
 use crate::database::{structures::DatabaseFile, operations::Database};
+use crate::utils::settings::settings;
 
 use super::{render::*, input_actions, menu_actions};
 use super::structures::*;
 use super::enums::*;
+use super::keymap::{Action, Keymap};
+use super::theme::Theme;
+use super::dialog::Dialog;
+use super::terminal::TerminalGuard;
+use super::text_area::TextArea;
 
-pub fn run_gui(db: &mut DatabaseFile) -> Result<(), anyhow::Error> {
-    enable_raw_mode().expect("can run in raw mode");
+/// `alternate_screen` keeps the GUI out of the scrollback of the terminal
+pub fn run_gui(db: &mut DatabaseFile, session: &mut Session, alternate_screen: bool) -> Result<(), anyhow::Error> {
+    // restores the terminal however the GUI is left
+    let _terminal_guard = TerminalGuard::enter(alternate_screen)?;
     let mut app = App::default();
+    let keymap = Keymap::new(&settings().keys);
+    let theme = Theme::active(settings());
 
     let (tx, rx) = mpsc::channel();
     let tick_rate = Duration::from_millis(200);
-    thread::spawn(move || {
+    // the thread is stopped before returning, otherwise it would read the input of later prompts
+    let running = Arc::new(AtomicBool::new(true));
+    let input_running = running.clone();
+    let input_thread = thread::spawn(move || {
         let mut last_tick = Instant::now();
-        loop {
+        while input_running.load(Ordering::Relaxed) {
             let timeout = tick_rate
                 .checked_sub(last_tick.elapsed())
                 .unwrap_or_else(|| Duration::from_secs(0));
 
-            if event::poll(timeout).expect("poll works") {
-                if let CEvent::Key(key) = event::read().expect("can read events") {
-                    tx.send(Event::Input(key)).expect("can send events");
-                }
+            // a failing terminal ends the thread, the GUI then stops with an error
+            match event::poll(timeout) {
+                std::result::Result::Ok(true) => match event::read() {
+                    std::result::Result::Ok(CEvent::Key(key)) => {
+                        if tx.send(Event::Input(key)).is_err() {
+                            break;
+                        }
+                    }
+                    std::result::Result::Ok(CEvent::Paste(text)) => {
+                        if tx.send(Event::Paste(text)).is_err() {
+                            break;
+                        }
+                    }
+                    std::result::Result::Ok(_) => {}
+                    Err(_) => break,
+                },
+                std::result::Result::Ok(false) => {}
+                Err(_) => break,
             }
 
             if last_tick.elapsed() >= tick_rate && tx.send(Event::Tick).is_ok() {
@@ -55,6 +81,9 @@ pub fn run_gui(db: &mut DatabaseFile) -> Result<(), anyhow::Error> {
     let mut password_entires_list_state = ListState::default();
     password_entires_list_state.select(Some(0));
 
+    let mut recycle_bin_list_state = ListState::default();
+    recycle_bin_list_state.select(Some(0));
+
     let mut detail_list_state = ListState::default();
     detail_list_state.select(Some(0));
     let mut attribute_count = 0;
@@ -65,130 +94,268 @@ pub fn run_gui(db: &mut DatabaseFile) -> Result<(), anyhow::Error> {
             let size = rect.size();
             let chunks = render_chunks(size);
 
-            let info = render_info();
-            let tabs = render_tabs(active_menu_item);
+            let info = render_info(app.notice.as_ref(), session.is_modified(db), session.read_only, &theme);
+            let tabs = render_tabs(active_menu_item, Some(&app.editor).filter(|_| matches!(app.input_mode, InputMode::Editing)), session.read_only, &keymap, &theme);
 
             rect.render_widget(tabs, chunks[0]);
-            match active_menu_item {
-                MenuItem::Home => rect.render_widget(render_home(), chunks[1]),
+            if let InputMode::Unlock = app.input_mode {
+                rect.render_widget(render_locked(&theme), chunks[1]);
+                menu_actions::unlock_popup(&app, &keymap, rect, &theme);
+            } else { match active_menu_item {
+                MenuItem::Home => rect.render_widget(render_home(&keymap, &theme), chunks[1]),
                 MenuItem::PasswordEntries => {
-                    menu_actions::password_entires_menu(&mut password_entires_list_state, db, rect, &chunks);
+                    menu_actions::password_entires_menu(&mut password_entires_list_state, db, rect, &chunks, &theme);
             },
                 MenuItem::SelctedEntry => {
                     attribute_count = display_selected_entry(db, &app, rect, &mut detail_list_state,
-                                                             password_entires_list_state.selected().unwrap(), &chunks, &show_value)
+                                                             password_entires_list_state.selected().unwrap_or(0), &chunks, &show_value, &theme)
                 },
-            }
+                MenuItem::RecycleBin => {
+                    menu_actions::recycle_bin_menu(&mut recycle_bin_list_state, db, rect, &chunks, &theme);
+                },
+            } }
             rect.render_widget(info, chunks[2]);
+            if let Some(dialog) = &app.dialog {
+                menu_actions::dialog_popup(dialog, db, &keymap, rect, &theme);
+            }
+            if let InputMode::SaveAs = app.input_mode {
+                menu_actions::save_as_popup(&app, &keymap, rect, &theme);
+            }
+            if let Some(scroll) = app.help {
+                menu_actions::help_popup(scroll, &keymap, active_menu_item, session.read_only, rect, &theme);
+            }
         })?;
 
-        match rx.recv()? {
-            Event::Tick => {},
+        let mut quit = false;
+
+        let event = rx.recv().map_err(|_| anyhow!("could not read the input of the terminal"))?;
+        if let Event::Input(_) | Event::Paste(_) = event {
+            app.last_input = Instant::now();
+        }
+        match event {
+            Event::Tick => {
+                app.expire_notice();
+                let clipboard_timeout = Duration::from_secs(settings().clipboard_timeout);
+                if app.copied.as_ref().map(|(_, at)| !clipboard_timeout.is_zero() && at.elapsed() >= clipboard_timeout).unwrap_or(false) {
+                    input_actions::clear_clipboard(&mut app);
+                }
+                let lock_timeout = Duration::from_secs(settings().lock_timeout);
+                if !lock_timeout.is_zero() && app.last_input.elapsed() >= lock_timeout && !matches!(app.input_mode, InputMode::Unlock) {
+                    // whatever was typed or asked is dropped, the screen must not show anything of the vault
+                    app.input_mode = InputMode::Unlock;
+                    app.editor = TextArea::default();
+                    app.input_error = None;
+                    app.dialog = None;
+                    app.message.clear();
+                    app.help = None;
+                    show_value = false;
+                }
+                let autosave_due = session.autosave.map(|x| session.last_save.elapsed() >= x).unwrap_or(false);
+                if autosave_due && !session.read_only && app.dialog.is_none() && session.is_modified(db) {
+                    input_actions::save(&mut app, session, db, false);
+                }
+            },
+            Event::Paste(text) => {
+                if !matches!(app.input_mode, InputMode::Navigation) && app.dialog.is_none() && app.help.is_none() {
+                    app.editor.insert_str(&text);
+                }
+            }
+            Event::Input(event) if app.help.is_some() => {
+                let scroll = app.help.unwrap_or_default();
+                app.help = match keymap.action(&event, active_menu_item) {
+                    Some(Action::Up) => Some(scroll.saturating_sub(1)),
+                    Some(Action::Down) => {
+                        let lines = help_text(&keymap, active_menu_item, session.read_only, &theme).len();
+                        Some((scroll + 1).min(lines.saturating_sub(1) as u16))
+                    }
+                    _ => None,
+                };
+            }
+            Event::Input(event) if app.dialog.is_some() => {
+                match (app.dialog.take(), keymap.dialog_action(&event)) {
+                    (Some(Dialog::Error { .. }), _) => {}
+                    (Some(Dialog::Confirm(PendingAction::RemoveEntry(id))), Some(Action::Yes)) => {
+                        db.remove_entry_by_id(id);
+                        input_actions::clamp_selection(&mut password_entires_list_state, db.entries.len());
+                    }
+                    (Some(Dialog::Confirm(PendingAction::PurgeEntry(id))), Some(Action::Yes)) => {
+                        db.purge_entry_by_id(id);
+                        input_actions::clamp_selection(&mut recycle_bin_list_state, db.recycle_bin.len());
+                    }
+                    (Some(Dialog::Confirm(PendingAction::ChangeType { id, .. })), Some(Action::Yes)) => {
+                        input_actions::change_type(id, &mut detail_list_state, db);
+                        show_value = false;
+                    }
+                    (Some(Dialog::Confirm(PendingAction::Quit)), Some(Action::Yes)) => quit = true,
+                    (Some(Dialog::Confirm(PendingAction::Quit)), _) if keymap.action(&event, active_menu_item) == Some(Action::Save) => {
+                        input_actions::save(&mut app, session, db, true);
+                        // a failed save or a file changed by another program opens its dialog instead
+                        quit = !session.is_modified(db);
+                    }
+                    (Some(Dialog::Confirm(PendingAction::ExternalChange)), Some(Action::Merge)) => {
+                        input_actions::merge_and_save(&mut app, session, db);
+                    }
+                    (Some(Dialog::Confirm(PendingAction::ExternalChange)), Some(Action::Overwrite)) => {
+                        let path = session.path.clone();
+                        input_actions::save_to_path(&mut app, session, db, path);
+                    }
+                    (Some(Dialog::Confirm(PendingAction::ExternalChange)), Some(Action::SaveAs)) => {
+                        app.input_mode = InputMode::SaveAs;
+                        app.editor = TextArea::new(&session.path.display().to_string(), false);
+                    }
+                    (Some(Dialog::Confirm(PendingAction::OverwriteFile(path))), Some(Action::Yes)) => {
+                        input_actions::save_to_path(&mut app, session, db, path);
+                    }
+                    (_, Some(Action::No)) | (_, Some(Action::Cancel)) => {}
+                    (dialog, _) => app.dialog = dialog,
+                }
+            }
             Event::Input(event) => {
                 match app.input_mode {
-                    InputMode::Navigation => match event.code {
-                        KeyCode::Char('a') => {
-                            if active_menu_item == MenuItem::PasswordEntries{
-                                db.add_empty_entry();
-                                active_menu_item = MenuItem::SelctedEntry;
-                                password_entires_list_state.select(Some(db.entries.len()-1));
-                            }
+                    InputMode::Navigation if session.read_only && keymap.action(&event, active_menu_item).map(|x| x.changes_vault()).unwrap_or(false) => {
+                        app.warn("Opened read-only, the vault can't be changed");
+                    }
+                    InputMode::Navigation => match keymap.action(&event, active_menu_item) {
+                        Some(Action::AddEntry) => {
+                            db.add_empty_entry();
+                            active_menu_item = MenuItem::SelctedEntry;
+                            password_entires_list_state.select(Some(db.entries.len()-1));
+                            input_actions::clamp_selection(&mut detail_list_state, db.entries[db.entries.len()-1].fields().len());
+                        }
+                        Some(Action::RecycleBin) => {
+                            active_menu_item = MenuItem::RecycleBin;
+                            show_value = false;
+                            input_actions::clamp_selection(&mut recycle_bin_list_state, db.recycle_bin.len());
                         }
-                        KeyCode::Char('c') => {
-                            input_actions::key_code_c(active_menu_item, &password_entires_list_state,
+                        Some(Action::CopyValue) => {
+                            input_actions::key_code_c(&mut app, active_menu_item, &password_entires_list_state,
                                                       &detail_list_state, db);
                         }
-                        KeyCode::Char('e') => {
-                            if active_menu_item == MenuItem::SelctedEntry{
+                        Some(Action::EditValue) => {
+                            if let Some(entry) = password_entires_list_state.selected().and_then(|x| db.entries.get(x)) {
                                 app.input_mode = InputMode::Editing;
-
-                                let index_entries = password_entires_list_state.selected().unwrap();
-                                let index_detail = detail_list_state.selected().unwrap();
-                                app.input = db.get_value_from_selected_detail(index_detail, db.entries[index_entries].id.clone());
-                                app.input_index = app.input.len()+1;
+                                let index_detail = detail_list_state.selected().unwrap_or(0);
+                                let multiline = entry.fields().get(index_detail).map(|x| x.multiline).unwrap_or(false);
+                                app.editor = TextArea::new(&db.get_value_from_selected_detail(index_detail, entry.id.clone()), multiline);
                             }
                         }
-                        KeyCode::Char('h') => {
+                        Some(Action::GenerateSecret) => {
+                            input_actions::key_code_g(&mut app, &password_entires_list_state, &detail_list_state, db);
+                        }
+                        Some(Action::Home) => {
                             active_menu_item = MenuItem::Home;
                             show_value = false;
                         }
-                        KeyCode::Char('p') => {
+                        Some(Action::PasswordEntries) => {
                             active_menu_item = MenuItem::PasswordEntries;
                             show_value = false;
                         }
-                        KeyCode::Char('q') => {
-                            disable_raw_mode()?;
-                            terminal.show_cursor()?;
-                            break;
+                        Some(Action::Quit) => {
+                            if session.is_modified(db) {
+                                app.confirm(PendingAction::Quit);
+                            } else {
+                                quit = true;
+                            }
+                        }
+                        Some(Action::RemoveEntry) => {
+                            if let Some(entry) = password_entires_list_state.selected().and_then(|index| db.entries.get(index)) {
+                                app.confirm(PendingAction::RemoveEntry(entry.id.clone()));
+                            }
+                        }
+                        Some(Action::RestoreEntry) => {
+                            if let Some(recycled) = recycle_bin_list_state.selected().and_then(|index| db.recycle_bin.get(index)) {
+                                db.restore_entry_by_id(recycled.entry.id.clone());
+                                input_actions::clamp_selection(&mut recycle_bin_list_state, db.recycle_bin.len());
+                            }
+                        }
+                        Some(Action::ChangeType) => {
+                            input_actions::key_code_t(&mut app, &password_entires_list_state, &mut detail_list_state, db);
+                            show_value = false;
                         }
-                        KeyCode::Char('r') =>{
-                            let password_len = db.entries.len();
-                            if active_menu_item == MenuItem::PasswordEntries && password_len != 0 && password_len > password_entires_list_state.selected().unwrap_or(0){
-                                db.remove_entry_by_id(db.entries[password_entires_list_state.selected().unwrap()].id.clone());
+                        Some(Action::PurgeEntry) => {
+                            if let Some(recycled) = recycle_bin_list_state.selected().and_then(|index| db.recycle_bin.get(index)) {
+                                app.confirm(PendingAction::PurgeEntry(recycled.entry.id.clone()));
                             }
                         }
-                        KeyCode::Char('s') => {
-                            if active_menu_item == MenuItem::PasswordEntries{
-                                let password_len = db.entries.len();
-                                if password_len != 0 && password_len > password_entires_list_state.selected().unwrap_or(0){
-                                    active_menu_item = MenuItem::SelctedEntry
-                                }
-                            } else if active_menu_item == MenuItem::SelctedEntry{
-                                show_value = !show_value;
+                        Some(Action::SelectEntry) => {
+                            if let Some(entry) = db.entries.get(password_entires_list_state.selected().unwrap_or(0)) {
+                                active_menu_item = MenuItem::SelctedEntry;
+                                input_actions::clamp_selection(&mut detail_list_state, entry.fields().len());
                             }
                         }
-                        KeyCode::Down => {
+                        Some(Action::ShowSecret) => show_value = !show_value,
+                        Some(Action::Save) => input_actions::save(&mut app, session, db, true),
+                        Some(Action::Help) => app.help = Some(0),
+                        Some(Action::Down) => {
                             input_actions::key_down(active_menu_item, &mut password_entires_list_state,
-                                                    &mut detail_list_state, db, &attribute_count);
+                                                    &mut detail_list_state, &mut recycle_bin_list_state, db, &attribute_count);
                         }
-                        KeyCode::Up => {
+                        Some(Action::Up) => {
                             input_actions::key_up(active_menu_item, &mut password_entires_list_state,
-                                                  &mut detail_list_state, db, &attribute_count);
+                                                  &mut detail_list_state, &mut recycle_bin_list_state, db, &attribute_count);
+                        }
+                        _ => {}
+                    }
+                    // the vault stays locked, so it can be left without the password. Unsaved changes are asked for
+                    InputMode::Unlock if keymap.quits_input(&event, active_menu_item)
+                        || keymap.input_action(&event).map(|(x, _)| x) == Some(Action::Cancel) => {
+                        app.editor.clear();
+                        if session.is_modified(db) {
+                            app.confirm(PendingAction::Quit);
+                        } else {
+                            quit = true;
                         }
-                        _ => {} 
                     }
-                    InputMode::Editing => match event.code{
-                        KeyCode::Enter => {
+                    InputMode::Editing | InputMode::SaveAs | InputMode::Unlock => match keymap.input_action(&event) {
+                        Some((Action::Confirm, _)) if matches!(app.input_mode, InputMode::Unlock) => {
+                            if app.editor.text() == db.password {
+                                app.input_mode = InputMode::Navigation;
+                                app.input_error = None;
+                            } else {
+                                app.input_error = Some(String::from("Wrong password"));
+                            }
+                            app.editor = TextArea::default();
+                        }
+                        Some((Action::Confirm, _)) if matches!(app.input_mode, InputMode::SaveAs) => {
+                            let path = PathBuf::from(app.editor.text().trim());
+                            app.input_mode = InputMode::Navigation;
+                            app.editor = TextArea::default();
+                            input_actions::save_as(&mut app, session, db, path);
+                        }
+                        Some((Action::Confirm, _)) => {
                             input_actions::key_enter(&mut app, &mut password_entires_list_state, &mut detail_list_state, db)
                         }
-                        KeyCode::Esc => {
+                        Some((Action::Cancel, _)) => {
                             app.input_mode = InputMode::Navigation;
-                            app.input_index = 0;
-                            app.input = String::new();
-                        }
-                        KeyCode::Char(c) => {
-                            if app.input_index == 0{
-                                app.input.insert(app.input_index, c);
-                                app.input_index += 1;
-                            } else{
-                                app.input.insert(app.input_index-1, c);
-                            }
-                            
-                            app.input_index += 1;
-                            
-                        }
-                        KeyCode::Backspace => {
-                            if !app.input.is_empty() && app.input_index > 1{
-                                app.input.remove(app.input_index-2);
-                                app.input_index -= 1;                                
-                            }
+                            app.editor = TextArea::default();
+                            app.input_error = None;
                         }
-                        KeyCode::Right =>{
-                            if app.input_index < app.input.len()+1{
-                                app.input_index += 1
-                            }
+                        Some((Action::Newline, _)) => app.editor.newline(),
+                        Some((Action::DeleteBack, _)) => app.editor.delete_back(),
+                        Some((Action::DeleteForward, _)) => app.editor.delete_forward(),
+                        Some((Action::SelectAll, _)) => app.editor.select_all(),
+                        Some((Action::Copy, _)) => input_actions::copy_selection(&mut app, false),
+                        Some((Action::Cut, _)) => input_actions::copy_selection(&mut app, true),
+                        Some((Action::Paste, _)) => input_actions::paste(&mut app),
+                        Some((action, select)) => if let Some(movement) = action.movement() {
+                            app.editor.move_cursor(movement, select);
                         }
-                        KeyCode::Left =>{
-                            if app.input_index > 1{
-                                app.input_index -= 1
-                            }
+                        None => if let KeyCode::Char(c) = event.code {
+                            app.editor.insert_char(c);
                         }
-                        _ => {}
                     }
                 }
             }
         }
+        if quit {
+            if settings().clipboard_timeout > 0 {
+                input_actions::clear_clipboard(&mut app);
+            }
+            break;
+        }
     }
+    running.store(false, Ordering::Relaxed);
+    let _ = input_thread.join();
     Ok(())
 }
 
@@ -1,15 +1,24 @@
 use super::enums::MenuItem;
 use super::enums::*;
 use super::structures::*;
+use super::text_area::TextArea;
+use crate::database::file_state::{changed_on_disk, merge_from_disk, save_to};
+use crate::database::lock::{LockAttempt, VaultLock};
 use crate::database::operations::Database;
 use crate::database::structures::DatabaseFile;
+use chrono::Local;
 use cli_clipboard::{ClipboardContext, ClipboardProvider};
+use crate::utils::generator::generate_password;
+use crate::utils::settings::settings;
+use std::path::PathBuf;
+use std::time::Instant;
 use tui::widgets::ListState;
 
 pub fn key_down(
     active_menu_item: MenuItem,
     password_entires_list_state: &mut ListState,
     detail_list_state: &mut ListState,
+    recycle_bin_list_state: &mut ListState,
     db: &DatabaseFile,
     attribute_count: &usize,
 ) {
@@ -33,6 +42,18 @@ pub fn key_down(
                 detail_list_state.select(Some(selected + 1));
             }
         }
+    } else if active_menu_item == MenuItem::RecycleBin {
+        let amount_entries = db.recycle_bin.len();
+        if amount_entries == 0 {
+            return;
+        }
+        if let Some(selected) = recycle_bin_list_state.selected() {
+            if selected >= amount_entries - 1 {
+                recycle_bin_list_state.select(Some(0));
+            } else {
+                recycle_bin_list_state.select(Some(selected + 1));
+            }
+        }
     }
 }
 
@@ -40,6 +61,7 @@ pub fn key_up(
     active_menu_item: MenuItem,
     password_entires_list_state: &mut ListState,
     detail_list_state: &mut ListState,
+    recycle_bin_list_state: &mut ListState,
     db: &DatabaseFile,
     attribute_count: &usize,
 ) {
@@ -63,6 +85,26 @@ pub fn key_up(
                 detail_list_state.select(Some(attribute_count - 1));
             }
         }
+    } else if active_menu_item == MenuItem::RecycleBin {
+        let amount_entries = db.recycle_bin.len();
+        if amount_entries == 0 {
+            return;
+        }
+        if let Some(selected) = recycle_bin_list_state.selected() {
+            if selected > 0 {
+                recycle_bin_list_state.select(Some(selected - 1));
+            } else {
+                recycle_bin_list_state.select(Some(amount_entries - 1));
+            }
+        }
+    }
+}
+
+/// Keeps the selection inside the list after entries were removed
+pub fn clamp_selection(list_state: &mut ListState, amount_entries: usize) {
+    let selected = list_state.selected().unwrap_or(0);
+    if selected >= amount_entries {
+        list_state.select(Some(amount_entries.saturating_sub(1)));
     }
 }
 
@@ -72,39 +114,221 @@ pub fn key_enter(
     detail_list_state: &mut ListState,
     db: &mut DatabaseFile,
 ) {
-    app.message.push(app.input.drain(..).collect());
-    let index_entries = password_entires_list_state.selected().unwrap();
-    let index_detail = detail_list_state.selected().unwrap();
-
-    db.update_entry(
-        index_detail,
-        db.entries[index_entries].id.clone(),
-        app.message.clone(),
-    );
+    app.message.push(app.editor.text());
+    let id = match password_entires_list_state
+        .selected()
+        .and_then(|x| db.entries.get(x))
+    {
+        Some(entry) => entry.id.clone(),
+        None => return,
+    };
+    let index_detail = detail_list_state.selected().unwrap_or(0);
+
+    if let Err(err) = db.update_entry(index_detail, id, app.message.clone()) {
+        // stay in editing mode so the user can correct the input
+        app.input_error = Some(err.to_string());
+        return;
+    }
     app.input_mode = InputMode::Navigation;
-    app.input_index = 0;
-    app.input = String::new();
+    app.editor = TextArea::default();
+    app.input_error = None;
+}
+
+/// Switches the selected entry to the next entry type, asks first if that would hide, reveal or invalidate
+/// any of its values
+pub fn key_code_t(
+    app: &mut App,
+    password_entires_list_state: &ListState,
+    detail_list_state: &mut ListState,
+    db: &mut DatabaseFile,
+) {
+    let entry = match password_entires_list_state
+        .selected()
+        .and_then(|index| db.entries.get(index))
+    {
+        Some(entry) => entry,
+        None => return,
+    };
+    let issues = entry.type_change_issues(&entry.entry_type.next());
+    if issues.is_empty() {
+        change_type(entry.id.clone(), detail_list_state, db);
+    } else {
+        app.confirm(PendingAction::ChangeType {
+            id: entry.id.clone(),
+            issues,
+        });
+    }
+}
+
+pub fn change_type(id: String, detail_list_state: &mut ListState, db: &mut DatabaseFile) {
+    if let Some(entry) = db.entries.iter_mut().find(|x| x.id == id) {
+        entry.entry_type = entry.entry_type.next();
+        entry.last_modified = Local::now().to_string();
+        clamp_selection(detail_list_state, entry.fields().len());
+    }
 }
 
 pub fn key_code_c(
+    app: &mut App,
     active_menu_item: MenuItem,
     password_entires_list_state: &ListState,
     detail_list_state: &ListState,
     db: &DatabaseFile,
 ) {
-    if active_menu_item == MenuItem::SelctedEntry {
-        let index_entries = password_entires_list_state.selected().unwrap();
-        let index_detail = detail_list_state.selected().unwrap();
-
-        let value = match index_detail {
-            0 => db.entries[index_entries].title.clone(),
-            1 => db.entries[index_entries].name.clone(),
-            2 => db.entries[index_entries].value.clone(),
-            3 => db.entries[index_entries].url.clone(),
-            4 => db.entries[index_entries].comment.clone(),
-            _ => "".to_owned(),
-        };
-        let mut ctx = ClipboardContext::new().unwrap();
-        ctx.set_contents(value).unwrap();
+    if active_menu_item != MenuItem::SelctedEntry {
+        return;
+    }
+    let entry = match password_entires_list_state
+        .selected()
+        .and_then(|x| db.entries.get(x))
+    {
+        Some(entry) => entry,
+        None => return,
+    };
+    let index_detail = detail_list_state.selected().unwrap_or(0);
+    let value = db.get_value_from_selected_detail(index_detail, entry.id.clone());
+    let copied = ClipboardContext::new().and_then(|mut ctx| ctx.set_contents(value.clone()));
+    match copied {
+        Ok(()) => {
+            app.notify("Copied to the clipboard");
+            app.copied = Some((value, Instant::now()));
+        }
+        Err(err) => app.error("Copying failed", err),
+    }
+}
+
+/// Empties the clipboard if it still contains the copied value, something copied later is kept
+pub fn clear_clipboard(app: &mut App) {
+    if let Some((value, _)) = app.copied.take() {
+        if let Ok(mut ctx) = ClipboardContext::new() {
+            if ctx.get_contents().map(|x| x == value).unwrap_or(false) {
+                let _ = ctx.set_contents(String::new());
+            }
+        }
+    }
+}
+
+/// Copies the selection of the input field, `cut` removes it afterwards. It is removed from the clipboard
+/// after the clipboard timeout like copied values
+pub fn copy_selection(app: &mut App, cut: bool) {
+    let text = match app.editor.selected_text() {
+        Some(text) => text,
+        None => return,
+    };
+    match ClipboardContext::new().and_then(|mut ctx| ctx.set_contents(text.clone())) {
+        Ok(()) => {
+            if cut {
+                app.editor.delete_selection();
+            }
+            app.copied = Some((text, Instant::now()));
+        }
+        Err(err) => app.error("Copying failed", err),
+    }
+}
+
+/// Inserts the clipboard into the input field, pasting into the terminal works as well
+pub fn paste(app: &mut App) {
+    match ClipboardContext::new().and_then(|mut ctx| ctx.get_contents()) {
+        Ok(text) => app.editor.insert_str(&text),
+        Err(err) => app.error("Pasting failed", err),
+    }
+}
+
+/// Replaces the selected secret field with a generated password
+pub fn key_code_g(
+    app: &mut App,
+    password_entires_list_state: &ListState,
+    detail_list_state: &ListState,
+    db: &mut DatabaseFile,
+) {
+    let entry = match password_entires_list_state.selected().and_then(|x| db.entries.get(x)) {
+        Some(entry) => entry,
+        None => return,
+    };
+    let index_detail = detail_list_state.selected().unwrap_or(0);
+    if !entry.fields().get(index_detail).map(|x| x.secret).unwrap_or(false) {
+        app.warn("Passwords can only be generated for secret fields");
+        return;
+    }
+    let password = generate_password(&settings().generator);
+    let id = entry.id.clone();
+    match db.update_entry(index_detail, id, vec![password]) {
+        Ok(()) => app.notify("Generated a new password"),
+        Err(err) => app.error("Generating failed", err),
+    }
+}
+
+/// Saves the vault with `w` and for autosave. If another program changed the file meanwhile `ask`
+/// lets the user decide how to go on, autosave waits for that instead
+pub fn save(app: &mut App, session: &mut Session, db: &mut DatabaseFile, ask: bool) {
+    if session.read_only {
+        app.warn("Opened read-only, the vault can't be saved");
+        return;
+    }
+    if changed_on_disk(db, &session.path) {
+        if ask {
+            app.confirm(PendingAction::ExternalChange);
+        } else {
+            // checked again after the next interval
+            session.last_save = Instant::now();
+            app.warn("Autosave paused, the file was changed by another program. Save to resolve it");
+        }
+        return;
+    }
+    let path = session.path.clone();
+    save_to_path(app, session, db, path);
+}
+
+/// Saves to the path typed in with save as, an existing file other than the vault is only replaced after asking
+pub fn save_as(app: &mut App, session: &mut Session, db: &mut DatabaseFile, path: PathBuf) {
+    if path != session.path && path.exists() {
+        app.confirm(PendingAction::OverwriteFile(path));
+    } else {
+        save_to_path(app, session, db, path);
+    }
+}
+
+/// Writes the vault without checking the file, later saves go to `path` as well.
+/// The lock moves along to a new path, it fails if another process has that file open
+pub fn save_to_path(app: &mut App, session: &mut Session, db: &mut DatabaseFile, path: PathBuf) {
+    let new_lock = if path == session.path {
+        None
+    } else {
+        match VaultLock::acquire(&path) {
+            Ok(LockAttempt::Acquired(lock)) => Some(lock),
+            Ok(LockAttempt::Held(holder)) => return app.error("Saving failed", holder),
+            Err(err) => return app.error("Saving failed", err),
+        }
+    };
+    match save_to(db, &path) {
+        Ok(()) => {
+            app.notify(format!("Saved {} at {}", path.display(), Local::now().format("%H:%M:%S")));
+            if new_lock.is_some() {
+                session.lock = new_lock;
+            }
+            session.path = path;
+            session.saved = db.clone();
+            session.last_save = Instant::now();
+        }
+        Err(err) => app.error("Saving failed", err),
+    }
+}
+
+/// Merges the changes of the other program into the vault and saves the result
+pub fn merge_and_save(app: &mut App, session: &mut Session, db: &mut DatabaseFile) {
+    let report = match merge_from_disk(db, &session.path, &session.saved) {
+        Ok(report) => report,
+        Err(err) => {
+            app.error("Merging failed", err);
+            return;
+        }
+    };
+    let path = session.path.clone();
+    save_to_path(app, session, db, path);
+    if !session.is_modified(db) {
+        app.notify(format!(
+            "Merged {} changes of the other program and saved, {} conflicts kept the entry changed last",
+            report.from_b, report.conflicts
+        ));
     }
 }
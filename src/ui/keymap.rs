@@ -0,0 +1,578 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+use super::enums::MenuItem;
+use super::text_area::Movement;
+
+/// Everything the keys of the navigation mode can do, the names are used in the `[keys]` config
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Home,
+    PasswordEntries,
+    SelectEntry,
+    EditValue,
+    CopyValue,
+    ShowSecret,
+    ChangeType,
+    GenerateSecret,
+    AddEntry,
+    RemoveEntry,
+    RestoreEntry,
+    RecycleBin,
+    PurgeEntry,
+    Save,
+    Quit,
+    Up,
+    Down,
+    Help,
+    Confirm,
+    Cancel,
+    Newline,
+    CursorLeft,
+    CursorRight,
+    CursorUp,
+    CursorDown,
+    WordLeft,
+    WordRight,
+    LineStart,
+    LineEnd,
+    DeleteBack,
+    DeleteForward,
+    SelectAll,
+    Copy,
+    Cut,
+    Paste,
+    Yes,
+    No,
+    Merge,
+    Overwrite,
+    SaveAs,
+}
+
+impl Action {
+    /// In the order of the tab menu and the help
+    pub const ALL: [Action; 40] = [
+        Action::Home,
+        Action::PasswordEntries,
+        Action::SelectEntry,
+        Action::EditValue,
+        Action::CopyValue,
+        Action::ShowSecret,
+        Action::ChangeType,
+        Action::GenerateSecret,
+        Action::AddEntry,
+        Action::RemoveEntry,
+        Action::RestoreEntry,
+        Action::RecycleBin,
+        Action::PurgeEntry,
+        Action::Save,
+        Action::Quit,
+        Action::Up,
+        Action::Down,
+        Action::Help,
+        Action::Confirm,
+        Action::Cancel,
+        Action::Newline,
+        Action::CursorLeft,
+        Action::CursorRight,
+        Action::CursorUp,
+        Action::CursorDown,
+        Action::WordLeft,
+        Action::WordRight,
+        Action::LineStart,
+        Action::LineEnd,
+        Action::DeleteBack,
+        Action::DeleteForward,
+        Action::SelectAll,
+        Action::Copy,
+        Action::Cut,
+        Action::Paste,
+        Action::Yes,
+        Action::No,
+        Action::Merge,
+        Action::Overwrite,
+        Action::SaveAs,
+    ];
+
+    /// Name in the config and the tab menu
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Home => "home",
+            Action::PasswordEntries => "password-entries",
+            Action::SelectEntry => "select-entry",
+            Action::EditValue => "edit-value",
+            Action::CopyValue => "copy-value",
+            Action::ShowSecret => "show-secret",
+            Action::ChangeType => "change-type",
+            Action::GenerateSecret => "generate-secret",
+            Action::AddEntry => "add-entry",
+            Action::RemoveEntry => "remove-entry",
+            Action::RestoreEntry => "restore-entry",
+            Action::RecycleBin => "recycle-bin",
+            Action::PurgeEntry => "purge-entry",
+            Action::Save => "save",
+            Action::Quit => "quit",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Help => "help",
+            Action::Confirm => "confirm",
+            Action::Cancel => "cancel",
+            Action::Newline => "newline",
+            Action::CursorLeft => "cursor-left",
+            Action::CursorRight => "cursor-right",
+            Action::CursorUp => "cursor-up",
+            Action::CursorDown => "cursor-down",
+            Action::WordLeft => "word-left",
+            Action::WordRight => "word-right",
+            Action::LineStart => "line-start",
+            Action::LineEnd => "line-end",
+            Action::DeleteBack => "delete-back",
+            Action::DeleteForward => "delete-forward",
+            Action::SelectAll => "select-all",
+            Action::Copy => "copy",
+            Action::Cut => "cut",
+            Action::Paste => "paste",
+            Action::Yes => "yes",
+            Action::No => "no",
+            Action::Merge => "merge",
+            Action::Overwrite => "overwrite",
+            Action::SaveAs => "save-as",
+        }
+    }
+
+    /// Completes "Press 'x' to ..." in the help texts
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Home => "go back to this screen",
+            Action::PasswordEntries => "access password entries",
+            Action::SelectEntry => "select an entry",
+            Action::EditValue => "edit the selected field",
+            Action::CopyValue => "copy the selected field",
+            Action::ShowSecret => "show or hide secret fields",
+            Action::ChangeType => "change the type of the entry",
+            Action::GenerateSecret => "generate a password for the selected field",
+            Action::AddEntry => "add new entries",
+            Action::RemoveEntry => "move an entry to the recycle bin",
+            Action::RestoreEntry => "restore an entry from the recycle bin",
+            Action::RecycleBin => "open the recycle bin",
+            Action::PurgeEntry => "delete an entry permanently",
+            Action::Save => "save the vault",
+            Action::Quit => "quit",
+            Action::Up => "move up",
+            Action::Down => "move down",
+            Action::Help => "show all keys",
+            Action::Confirm => "take over the input",
+            Action::Cancel => "leave the input without changes",
+            Action::Newline => "start a new line in comments and notes",
+            Action::CursorLeft => "move the cursor left",
+            Action::CursorRight => "move the cursor right",
+            Action::CursorUp => "move the cursor a line up",
+            Action::CursorDown => "move the cursor a line down",
+            Action::WordLeft => "move the cursor to the previous word",
+            Action::WordRight => "move the cursor to the next word",
+            Action::LineStart => "move the cursor to the start of the line",
+            Action::LineEnd => "move the cursor to the end of the line",
+            Action::DeleteBack => "delete the character before the cursor",
+            Action::DeleteForward => "delete the character after the cursor",
+            Action::SelectAll => "select the whole input",
+            Action::Copy => "copy the selection",
+            Action::Cut => "cut the selection",
+            Action::Paste => "paste from the clipboard",
+            Action::Yes => "answer a question with yes",
+            Action::No => "answer a question with no",
+            Action::Merge => "merge a vault changed by another program with this one",
+            Action::Overwrite => "overwrite a vault changed by another program",
+            Action::SaveAs => "save to another file instead of a changed vault",
+        }
+    }
+
+    /// Actions of the navigation in the menu, input and dialog actions are never available there
+    pub fn available_in(&self, menu: MenuItem) -> bool {
+        match self {
+            action if action.is_input() || action.is_dialog() => false,
+            Action::SelectEntry | Action::AddEntry | Action::RemoveEntry => {
+                menu == MenuItem::PasswordEntries
+            }
+            Action::EditValue
+            | Action::CopyValue
+            | Action::ShowSecret
+            | Action::ChangeType
+            | Action::GenerateSecret => menu == MenuItem::SelctedEntry,
+            Action::RestoreEntry | Action::PurgeEntry => menu == MenuItem::RecycleBin,
+            _ => true,
+        }
+    }
+
+    /// Disabled when the vault is opened read-only
+    pub fn changes_vault(&self) -> bool {
+        matches!(
+            self,
+            Action::EditValue
+                | Action::ChangeType
+                | Action::GenerateSecret
+                | Action::AddEntry
+                | Action::RemoveEntry
+                | Action::RestoreEntry
+                | Action::PurgeEntry
+                | Action::Save
+        )
+    }
+
+    /// Actions of input fields like editing a value, saving as or unlocking
+    pub fn is_input(&self) -> bool {
+        matches!(
+            self,
+            Action::Confirm
+                | Action::Cancel
+                | Action::Newline
+                | Action::DeleteBack
+                | Action::DeleteForward
+                | Action::SelectAll
+                | Action::Copy
+                | Action::Cut
+                | Action::Paste
+        ) || self.movement().is_some()
+    }
+
+    /// Answers of the dialogs, cancel closes them as well
+    pub fn is_dialog(&self) -> bool {
+        matches!(
+            self,
+            Action::Yes | Action::No | Action::Merge | Action::Overwrite | Action::SaveAs
+        )
+    }
+
+    /// How the cursor of an input field moves, with shift it selects the text on the way
+    pub fn movement(&self) -> Option<Movement> {
+        match self {
+            Action::CursorLeft => Some(Movement::Left),
+            Action::CursorRight => Some(Movement::Right),
+            Action::CursorUp => Some(Movement::Up),
+            Action::CursorDown => Some(Movement::Down),
+            Action::WordLeft => Some(Movement::WordLeft),
+            Action::WordRight => Some(Movement::WordRight),
+            Action::LineStart => Some(Movement::LineStart),
+            Action::LineEnd => Some(Movement::LineEnd),
+            _ => None,
+        }
+    }
+
+    /// Moving around is left out of the tab menu to keep it short
+    pub fn in_menu(&self) -> bool {
+        !matches!(self, Action::Up | Action::Down) && !self.is_input() && !self.is_dialog()
+    }
+
+    fn default_keys(&self) -> Vec<KeyBinding> {
+        let keys: &[&str] = match self {
+            Action::Home => &["h"],
+            Action::PasswordEntries => &["p"],
+            Action::SelectEntry | Action::ShowSecret => &["s"],
+            Action::EditValue => &["e"],
+            Action::CopyValue => &["c"],
+            Action::ChangeType => &["t"],
+            Action::GenerateSecret => &["g"],
+            Action::AddEntry => &["a"],
+            Action::RemoveEntry | Action::RestoreEntry => &["r"],
+            Action::RecycleBin => &["b"],
+            Action::PurgeEntry => &["x"],
+            Action::Save => &["w", "ctrl+s"],
+            Action::Quit => &["q"],
+            Action::Up => &["up", "k"],
+            Action::Down => &["down", "j"],
+            Action::Help => &["?"],
+            Action::Confirm => &["enter"],
+            Action::Cancel => &["esc"],
+            Action::Newline => &["alt+enter", "ctrl+o"],
+            Action::CursorLeft => &["left"],
+            Action::CursorRight => &["right"],
+            Action::CursorUp => &["up"],
+            Action::CursorDown => &["down"],
+            Action::WordLeft => &["ctrl+left", "alt+b"],
+            Action::WordRight => &["ctrl+right", "alt+f"],
+            Action::LineStart => &["home"],
+            Action::LineEnd => &["end"],
+            Action::DeleteBack => &["backspace"],
+            Action::DeleteForward => &["delete"],
+            Action::SelectAll => &["ctrl+a"],
+            Action::Copy => &["ctrl+c"],
+            Action::Cut => &["ctrl+x"],
+            Action::Paste => &["ctrl+v"],
+            Action::Yes => &["y"],
+            Action::No => &["n"],
+            Action::Merge => &["m"],
+            Action::Overwrite => &["o"],
+            Action::SaveAs => &["s"],
+        };
+        keys.iter()
+            .map(|x| KeyBinding::try_from(x.to_string()).expect("default keys are valid"))
+            .collect()
+    }
+}
+
+/// A key with modifiers like `ctrl+s`, `j` or `pagedown`
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "String")]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl TryFrom<String> for KeyBinding {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<KeyBinding, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = text.split('+').collect();
+        // `+` itself is a key as well
+        let key = match parts.pop() {
+            Some("") if text.ends_with('+') => {
+                parts.pop();
+                "+"
+            }
+            Some(key) => key,
+            None => "",
+        };
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier '{}' in '{}'", modifier, text)),
+            };
+        }
+        let mut chars = key.chars();
+        let mut code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "space" => KeyCode::Char(' '),
+                name => match name.strip_prefix('f').and_then(|x| x.parse().ok()) {
+                    Some(number) if (1..=12).contains(&number) => KeyCode::F(number),
+                    _ => return Err(format!("unknown key '{}'", text)),
+                },
+            },
+        };
+        // terminals report shift+a as `A`
+        if let (KeyCode::Char(c), true) = (code, modifiers.contains(KeyModifiers::SHIFT)) {
+            if let Some(upper) = c.to_uppercase().next() {
+                code = KeyCode::Char(upper);
+                modifiers.remove(KeyModifiers::SHIFT);
+            }
+        }
+        Ok(KeyBinding { code, modifiers })
+    }
+}
+
+impl KeyBinding {
+    /// Characters without ctrl or alt, they are text in input fields
+    fn is_typed(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_))
+            && !self
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+
+    fn matches(&self, event: &KeyEvent) -> bool {
+        // the shift of a character is already part of the character itself
+        let relevant = match event.code {
+            KeyCode::Char(_) => KeyModifiers::CONTROL | KeyModifiers::ALT,
+            _ => KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT,
+        };
+        self.code == event.code && self.modifiers & relevant == event.modifiers & relevant
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl+"),
+            (KeyModifiers::ALT, "alt+"),
+            (KeyModifiers::SHIFT, "shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(number) => write!(f, "f{}", number),
+            KeyCode::PageUp => f.write_str("pageup"),
+            KeyCode::PageDown => f.write_str("pagedown"),
+            KeyCode::Backspace => f.write_str("backspace"),
+            code => write!(f, "{}", format!("{:?}", code).to_lowercase()),
+        }
+    }
+}
+
+/// One key or a list of keys in the config
+#[derive(Clone, Debug)]
+pub struct KeyBindings(Vec<KeyBinding>);
+
+impl<'de> Deserialize<'de> for KeyBindings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<KeyBindings, D::Error> {
+        struct KeysVisitor;
+
+        impl<'de> Visitor<'de> for KeysVisitor {
+            type Value = KeyBindings;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a key like \"ctrl+s\" or a list of keys")
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<KeyBindings, E> {
+                let key = KeyBinding::try_from(text.to_owned()).map_err(E::custom)?;
+                Ok(KeyBindings(vec![key]))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<KeyBindings, A::Error> {
+                let mut keys = Vec::new();
+                while let Some(key) = seq.next_element()? {
+                    keys.push(key);
+                }
+                Ok(KeyBindings(keys))
+            }
+        }
+
+        deserializer.deserialize_any(KeysVisitor)
+    }
+}
+
+/// The keys of every action, the defaults with the ones of the config replacing them
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<KeyBinding>)>,
+}
+
+impl Keymap {
+    pub fn new(config: &BTreeMap<Action, KeyBindings>) -> Keymap {
+        let bindings = Action::ALL
+            .iter()
+            .map(|action| {
+                let keys = match config.get(action) {
+                    Some(KeyBindings(keys)) => keys.clone(),
+                    None => action.default_keys(),
+                };
+                (*action, keys)
+            })
+            .collect();
+        Keymap { bindings }
+    }
+
+    /// The action of the key in the menu, keys can be shared by actions of different menus
+    pub fn action(&self, event: &KeyEvent, menu: MenuItem) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(action, keys)| {
+                action.available_in(menu) && keys.iter().any(|x| x.matches(event))
+            })
+            .map(|(action, _)| *action)
+    }
+
+    /// The action of a key in input fields and whether it selects, which is a cursor movement
+    /// with shift held in addition to its key
+    pub fn input_action(&self, event: &KeyEvent) -> Option<(Action, bool)> {
+        let find = |event: &KeyEvent| {
+            self.bindings
+                .iter()
+                .find(|(action, keys)| action.is_input() && keys.iter().any(|x| x.matches(event)))
+                .map(|(action, _)| *action)
+        };
+        if let Some(action) = find(event) {
+            return Some((action, false));
+        }
+        if !event.modifiers.contains(KeyModifiers::SHIFT) || matches!(event.code, KeyCode::Char(_)) {
+            return None;
+        }
+        let unshifted = KeyEvent::new(event.code, event.modifiers - KeyModifiers::SHIFT);
+        find(&unshifted)
+            .filter(|x| x.movement().is_some())
+            .map(|x| (x, true))
+    }
+
+    /// The answer of the key in a dialog, cancel closes it like no
+    pub fn dialog_action(&self, event: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(action, keys)| {
+                (action.is_dialog() || *action == Action::Cancel)
+                    && keys.iter().any(|x| x.matches(event))
+            })
+            .map(|(action, _)| *action)
+    }
+
+    /// A quit key which isn't typed as text, it also leaves the password prompt of a locked vault
+    pub fn quits_input(&self, event: &KeyEvent, menu: MenuItem) -> bool {
+        let typed = KeyBinding {
+            code: event.code,
+            modifiers: event.modifiers,
+        }
+        .is_typed();
+        !typed && self.action(event, menu) == Some(Action::Quit)
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyBinding] {
+        self.bindings
+            .iter()
+            .find(|(x, _)| *x == action)
+            .map(|(_, keys)| keys.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// The first key of the action for hints, None if it is unbound
+    pub fn key(&self, action: Action) -> Option<String> {
+        self.keys(action).first().map(|x| x.to_string())
+    }
+
+    /// Finds a key bound to two actions of the same menu, returns the first action and why
+    pub fn conflict(&self) -> Option<(Action, String)> {
+        let menus = [
+            MenuItem::Home,
+            MenuItem::PasswordEntries,
+            MenuItem::SelctedEntry,
+            MenuItem::RecycleBin,
+        ];
+        // save answers the question to quit with unsaved changes
+        let in_dialog = |x: &Action| x.is_dialog() || matches!(x, Action::Cancel | Action::Save);
+        for (index, (action, keys)) in self.bindings.iter().enumerate() {
+            if let Some(key) = keys.iter().find(|x| action.is_input() && x.is_typed()) {
+                return Some((
+                    *action,
+                    format!("'{}' is typed in input fields, add ctrl+ or alt+", key),
+                ));
+            }
+            for (other, other_keys) in self.bindings[index + 1..].iter() {
+                let shared_menu = menus
+                    .iter()
+                    .any(|x| action.available_in(*x) && other.available_in(*x))
+                    || (action.is_input() && other.is_input())
+                    || (in_dialog(action) && in_dialog(other));
+                if let Some(key) = keys.iter().find(|x| other_keys.contains(x)) {
+                    if shared_menu {
+                        return Some((
+                            *action,
+                            format!("'{}' is also bound to {}", key, other.name()),
+                        ));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
@@ -1,11 +1,16 @@
 use std::io::Stdout;
-use tui::{layout::{Layout, Direction, Constraint, Rect}, widgets::ListState, Frame, backend::CrosstermBackend};
-use crate::database::structures::DatabaseFile;
-use super::render::render_password_entires;
+use tui::{layout::{Layout, Direction, Constraint, Rect}, widgets::{Clear, ListState}, Frame, backend::CrosstermBackend};
+use crate::database::{structures::DatabaseFile, operations::Database};
+use super::enums::{MenuItem, PendingAction};
+use super::keymap::{Action, Keymap};
+use super::theme::Theme;
+use super::dialog::Dialog;
+use super::structures::App;
+use super::render::{render_password_entires, render_recycle_bin, render_confirmation, render_error, render_save_as, render_unlock, render_help, help_text, key_hint, centered_rect};
 
 
 pub fn password_entires_menu(password_entires_list_state: &mut ListState, db: &DatabaseFile, 
-    rect: &mut Frame<CrosstermBackend<Stdout>>, chunks: &[Rect]){
+    rect: &mut Frame<CrosstermBackend<Stdout>>, chunks: &[Rect], theme: &Theme){
 
     let password_entry_chunks = Layout::default()
         .direction(Direction::Horizontal)
@@ -13,7 +18,93 @@ pub fn password_entires_menu(password_entires_list_state: &mut ListState, db: &D
             [Constraint::Percentage(20), Constraint::Percentage(80)].as_ref(),
         )
         .split(chunks[1]);
-    let (left, right) = render_password_entires(password_entires_list_state, db);
+    let (left, right) = render_password_entires(password_entires_list_state, db, theme);
     rect.render_stateful_widget(left, password_entry_chunks[0], password_entires_list_state);
     rect.render_widget(right, password_entry_chunks[1]);
 }
+
+pub fn recycle_bin_menu(recycle_bin_list_state: &mut ListState, db: &DatabaseFile,
+    rect: &mut Frame<CrosstermBackend<Stdout>>, chunks: &[Rect], theme: &Theme){
+
+    let recycle_bin_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [Constraint::Percentage(20), Constraint::Percentage(80)].as_ref(),
+        )
+        .split(chunks[1]);
+    let (left, right) = render_recycle_bin(recycle_bin_list_state, db, theme);
+    rect.render_stateful_widget(left, recycle_bin_chunks[0], recycle_bin_list_state);
+    rect.render_widget(right, recycle_bin_chunks[1]);
+}
+
+pub fn dialog_popup(dialog: &Dialog, db: &DatabaseFile, keymap: &Keymap,
+    rect: &mut Frame<CrosstermBackend<Stdout>>, theme: &Theme){
+    let pending_action = match dialog {
+        Dialog::Confirm(pending_action) => pending_action,
+        Dialog::Error { title, message } => {
+            let area = centered_rect(50, 25, rect.size());
+            rect.render_widget(Clear, area);
+            rect.render_widget(render_error(title, message.clone(), theme), area);
+            return;
+        }
+    };
+
+    let mut options = format!("{} yes / {} no", key_hint(keymap, Action::Yes), key_hint(keymap, Action::No));
+    let question = match pending_action {
+        PendingAction::RemoveEntry(id) => format!(
+            "Move '{}' to the recycle bin?",
+            db.get_entry_by_id(id.clone()).map(|x| x.title.clone()).unwrap_or_default()
+        ),
+        PendingAction::PurgeEntry(id) => format!(
+            "Permanently delete '{}'? This can not be undone.",
+            db.recycle_bin.iter().find(|x| &x.entry.id == id).map(|x| x.entry.title.clone()).unwrap_or_default()
+        ),
+        PendingAction::ChangeType { id, issues } => {
+            let entry = db.get_entry_by_id(id.clone());
+            format!(
+                "Change '{}' to {}? {}.",
+                entry.map(|x| x.title.clone()).unwrap_or_default(),
+                entry.map(|x| x.entry_type.next().label()).unwrap_or_default(),
+                issues.join(", ")
+            )
+        }
+        PendingAction::ExternalChange => {
+            options = format!(
+                "{} merge both / {} overwrite / {} save as / {} cancel",
+                key_hint(keymap, Action::Merge),
+                key_hint(keymap, Action::Overwrite),
+                key_hint(keymap, Action::SaveAs),
+                key_hint(keymap, Action::Cancel)
+            );
+            String::from("The vault file was changed by another program since it was loaded.")
+        }
+        PendingAction::OverwriteFile(path) => format!("{} already exists, overwrite it?", path.display()),
+        PendingAction::Quit => match keymap.key(Action::Save) {
+            Some(key) => format!("Quit without saving the changes? Press '{}' to save them first.", key),
+            None => String::from("Quit without saving the changes?"),
+        },
+    };
+    let area = centered_rect(50, 25, rect.size());
+    rect.render_widget(Clear, area);
+    rect.render_widget(render_confirmation(question, options, theme), area);
+}
+
+pub fn save_as_popup(app: &App, keymap: &Keymap, rect: &mut Frame<CrosstermBackend<Stdout>>, theme: &Theme){
+    let area = centered_rect(60, 20, rect.size());
+    rect.render_widget(Clear, area);
+    rect.render_widget(render_save_as(app, keymap, area, theme), area);
+}
+
+pub fn unlock_popup(app: &App, keymap: &Keymap, rect: &mut Frame<CrosstermBackend<Stdout>>, theme: &Theme){
+    let area = centered_rect(60, 20, rect.size());
+    rect.render_widget(Clear, area);
+    rect.render_widget(render_unlock(app, keymap, area, theme), area);
+}
+
+pub fn help_popup(scroll: u16, keymap: &Keymap, active_menu_item: MenuItem, read_only: bool,
+    rect: &mut Frame<CrosstermBackend<Stdout>>, theme: &Theme){
+    let area = centered_rect(70, 80, rect.size());
+    rect.render_widget(Clear, area);
+    let text = help_text(keymap, active_menu_item, read_only, theme);
+    rect.render_widget(render_help(text, scroll, keymap, theme), area);
+}
@@ -4,3 +4,8 @@ pub mod structures;
 pub mod enums;
 pub mod input_actions;
 pub mod menu_actions;
+pub mod keymap;
+pub mod theme;
+pub mod dialog;
+pub mod terminal;
+pub mod text_area;
@@ -1,42 +1,49 @@
-use std::usize;
 use std::io::Stdout;
 
 use chrono::Local;
 use tui::{
     layout::{Alignment, Constraint, Layout, Direction, Rect},
-    style::{Color, Style, Modifier},
+    style::Style,
     text::{Span, Spans},
     widgets::{
-        Block, BorderType, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, Tabs,
+        Block, BorderType, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, Tabs, Wrap,
     },
     Frame,
     backend::CrosstermBackend,
 };
 
 use crate::database::structures::{PasswordEntry, EntryType, DatabaseFile};
-use super::enums::MenuItem;
-use super::structures::App; 
-
-pub fn render_home<'a>() -> Paragraph<'a> {
-    let home = Paragraph::new(vec![
+use crate::database::fields::FieldSlot;
+use super::enums::{InputMode, MenuItem};
+use super::keymap::{Action, Keymap};
+use super::theme::Theme;
+use super::dialog::{Notice, NoticeLevel};
+use super::structures::App;
+use super::text_area::{masked, TextArea, TextStyles}; 
+
+/// Actions explained on the home screen
+const HOME_ACTIONS: [Action; 7] = [Action::PasswordEntries, Action::AddEntry, Action::SelectEntry, Action::RemoveEntry, Action::RecycleBin, Action::Save, Action::Help];
+
+pub fn render_home<'a>(keymap: &Keymap, theme: &Theme) -> Paragraph<'a> {
+    let mut text = vec![
         Spans::from(vec![Span::styled(
             "Welcome to Vault",
-            Style::default().fg(Color::LightBlue),
+            theme.title,
         )]),
         Spans::from(vec![Span::raw("Your personal terminal password manager")]),
         Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::raw("Press 'p' to access password entries")]),
-        Spans::from(vec![Span::raw("Press 'a' to add new entries")]),
-        Spans::from(vec![Span::raw("Press 's' to select an entry")]),
-        Spans::from(vec![Span::raw("Press 'd' to delete an entry")]),
-
-
-    ])
+    ];
+    for action in HOME_ACTIONS {
+        if let Some(key) = keymap.key(action) {
+            text.push(Spans::from(vec![Span::raw(format!("Press '{}' to {}", key, action.description()))]));
+        }
+    }
+    let home = Paragraph::new(text)
     .alignment(Alignment::Center)
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .style(Style::default().fg(Color::White))
+            .style(theme.text)
             .title("Home")
             .border_type(BorderType::Plain),
     );
@@ -50,10 +57,19 @@ fn replace_if_empty(title: String) -> String{
     title
 }
 
-pub fn render_password_entires<'a>(password_entries_list_state: &ListState, db: &DatabaseFile) -> (List<'a>, Table<'a>){
+/// Value of the field in `slot` for the overview, secret fields stay hidden and types without such a field show nothing
+fn overview_value(entry: &PasswordEntry, slot: FieldSlot) -> String{
+    match entry.fields().iter().find(|x| x.slot == slot) {
+        Some(field) if field.secret => String::from("*****"),
+        Some(field) => entry.get_field(field),
+        None => String::new(),
+    }
+}
+
+pub fn render_password_entires<'a>(password_entries_list_state: &ListState, db: &DatabaseFile, theme: &Theme) -> (List<'a>, Table<'a>){
     let entires = Block::default()
     .borders(Borders::ALL)
-    .style(Style::default().fg(Color::White))
+    .style(theme.text)
     .title("Passwords")
     .border_type(BorderType::Plain);
 
@@ -72,117 +88,384 @@ pub fn render_password_entires<'a>(password_entries_list_state: &ListState, db:
         .get(
             password_entries_list_state
                 .selected()
-                .unwrap(),
+                .unwrap_or(0),
         )
         .unwrap_or(&PasswordEntry{id: String::from("1"), title: String::from("Empty"), 
             value: String::from("Empty"), name: String::from("Empty"), url: String::from("Empty"), 
-            comment: String::from("Empty"), entry_type: EntryType::ClassicPassword, last_modified: Local::today().to_string(),
-            files: Vec::new()})
+            comment: String::from("Empty"), entry_type: EntryType::ClassicPassword, last_modified: Local::now().to_string(),
+            files: Vec::new(), extra_fields: Vec::new(), group: String::new()})
         .clone();
 
     let list = List::new(items).block(entires).highlight_style(
-        Style::default()
-            .bg(Color::Red)
-            .fg(Color::Black)
-            .add_modifier(Modifier::BOLD),
+        theme.selection,
     );
     let entry_detail = Table::new(vec![Row::new(vec![
         Cell::from(Span::raw(selected_entry.title.clone())),
-        Cell::from(Span::raw(selected_entry.name.clone())),
-        Cell::from(Span::raw("*****")),
-        Cell::from(Span::raw(selected_entry.comment.clone())),
+        Cell::from(Span::raw(selected_entry.entry_type.label())),
+        Cell::from(Span::raw(overview_value(&selected_entry, FieldSlot::Name))),
+        Cell::from(Span::raw(overview_value(&selected_entry, FieldSlot::Value))),
+        Cell::from(Span::raw(overview_value(&selected_entry, FieldSlot::Comment))),
         Cell::from(Span::raw(selected_entry.last_modified.to_string())),
     ])])
     .header(Row::new(vec![
         Cell::from(Span::styled(
             "Title",
-            Style::default().add_modifier(Modifier::BOLD),
+            theme.header,
+        )),
+        Cell::from(Span::styled(
+            "Type",
+            theme.header,
         )),
         Cell::from(Span::styled(
             "Name",
-            Style::default().add_modifier(Modifier::BOLD),
+            theme.header,
         )),
         Cell::from(Span::styled(
             "Value",
-            Style::default().add_modifier(Modifier::BOLD),
+            theme.header,
         )),
         Cell::from(Span::styled(
             "Comment",
-            Style::default().add_modifier(Modifier::BOLD),
+            theme.header,
         )),
         Cell::from(Span::styled(
             "Last Modified at",
-            Style::default().add_modifier(Modifier::BOLD),
+            theme.header,
         )),
     ]))
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .style(Style::default().fg(Color::White))
+            .style(theme.text)
             .title("Detail")
             .border_type(BorderType::Plain),
     )
     .widths(&[
+        Constraint::Percentage(16),
+        Constraint::Percentage(16),
+        Constraint::Percentage(16),
+        Constraint::Percentage(16),
+        Constraint::Percentage(16),
         Constraint::Percentage(20),
-        Constraint::Percentage(20),
-        Constraint::Percentage(20),
-        Constraint::Percentage(20),
-        Constraint::Percentage(20),
     ]);
 
     (list, entry_detail)
 }
 
-pub fn render_info<'a>() -> Paragraph<'a>{
-    return Paragraph::new("FOSS password manager and more")
-    .style(Style::default().fg(Color::LightCyan))
+pub fn render_recycle_bin<'a>(recycle_bin_list_state: &ListState, db: &DatabaseFile, theme: &Theme) -> (List<'a>, Table<'a>){
+    let bin = Block::default()
+    .borders(Borders::ALL)
+    .style(theme.text)
+    .title("Recycle Bin")
+    .border_type(BorderType::Plain);
+
+    let items: Vec<_> = db.recycle_bin
+        .iter()
+        .map(|recycled| {
+            ListItem::new(Spans::from(vec![Span::styled(
+                replace_if_empty(recycled.entry.title.clone()),
+                Style::default(),
+            )]))
+        })
+        .collect();
+
+    let list = List::new(items).block(bin).highlight_style(
+        theme.selection,
+    );
+
+    let retention_days = db.config.recycle_bin_retention_days;
+    let rows = match recycle_bin_list_state.selected().and_then(|index| db.recycle_bin.get(index)) {
+        Some(recycled) => {
+            let purged_at = if retention_days == 0 {
+                String::from("never")
+            } else {
+                (recycled.deleted_at + chrono::Duration::days(retention_days.into())).format("%Y-%m-%d %H:%M").to_string()
+            };
+            vec![Row::new(vec![
+                Cell::from(Span::raw(recycled.entry.title.clone())),
+                Cell::from(Span::raw(recycled.entry.name.clone())),
+                Cell::from(Span::raw(recycled.deleted_at.format("%Y-%m-%d %H:%M").to_string())),
+                Cell::from(Span::raw(purged_at)),
+            ])]
+        }
+        None => Vec::new(),
+    };
+
+    let entry_detail = Table::new(rows)
+    .header(Row::new(vec![
+        Cell::from(Span::styled(
+            "Title",
+            theme.header,
+        )),
+        Cell::from(Span::styled(
+            "Name",
+            theme.header,
+        )),
+        Cell::from(Span::styled(
+            "Deleted at",
+            theme.header,
+        )),
+        Cell::from(Span::styled(
+            "Purged at",
+            theme.header,
+        )),
+    ]))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(theme.text)
+            .title("Detail")
+            .border_type(BorderType::Plain),
+    )
+    .widths(&[
+        Constraint::Percentage(25),
+        Constraint::Percentage(25),
+        Constraint::Percentage(25),
+        Constraint::Percentage(25),
+    ]);
+
+    (list, entry_detail)
+}
+
+pub fn render_confirmation<'a>(question: String, options: String, theme: &Theme) -> Paragraph<'a>{
+    Paragraph::new(vec![
+        Spans::from(vec![Span::raw(question)]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::styled(options, theme.accent)]),
+    ])
     .alignment(Alignment::Center)
+    .wrap(Wrap { trim: true })
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .style(Style::default().fg(Color::White))
-            .title("Info")
+            .style(theme.text)
+            .title("Confirm")
             .border_type(BorderType::Plain),
-    );
+    )
 }
 
-pub fn render_tabs<'a>( active_menu_item: MenuItem) -> Tabs<'a>{
-    return Tabs::new(get_menu_for_mode(&active_menu_item))
-    .select(active_menu_item.into())
-    .block(Block::default().title("Menu").borders(Borders::ALL))
-    .style(Style::default().fg(Color::White))
-    .highlight_style(Style::default().fg(Color::Yellow))
-    .divider(Span::raw("|"));
+pub fn render_error<'a>(title: &str, message: String, theme: &Theme) -> Paragraph<'a>{
+    Paragraph::new(vec![
+        Spans::from(vec![Span::raw(message)]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::styled("Press any key to continue", theme.accent)]),
+    ])
+    .alignment(Alignment::Center)
+    .wrap(Wrap { trim: true })
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(theme.text)
+            .title(Span::styled(title.to_owned(), theme.error))
+            .border_type(BorderType::Plain),
+    )
+}
+
+/// Returns a rect with the given percentage of the available space in the middle of `area`
+pub fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(vertical[1])[1]
 }
 
+pub fn render_info<'a>(notice: Option<&Notice>, modified: bool, read_only: bool, theme: &Theme) -> Paragraph<'a>{
+    let title = if read_only {
+        Span::styled("Info - read-only", theme.warning)
+    } else if modified {
+        Span::styled("Info - modified", theme.accent)
+    } else {
+        Span::raw("Info")
+    };
+    let text = match notice {
+        Some(notice) if notice.level == NoticeLevel::Warning => Span::styled(notice.text.clone(), theme.warning),
+        Some(notice) => Span::styled(notice.text.clone(), theme.status),
+        None => Span::styled("FOSS password manager and more", theme.status),
+    };
+    Paragraph::new(Spans::from(text))
+    .alignment(Alignment::Center)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(theme.text)
+            .title(title)
+            .border_type(BorderType::Plain),
+    )
+}
 
-fn get_menu_for_mode<'a>(active_menu_item: &MenuItem) -> Vec<Spans<'a>> {
-    let men = match active_menu_item {
-        MenuItem::SelctedEntry => vec!["home", "password-entries", "edit-value", "ESC-quit-edit", "copy-value", "show-secret", "quit"],
-        MenuItem::PasswordEntries => vec!["home", "password-entries", "select-entry", "add-entry", "remove-entry", "quit"],
-        _ => vec!["home", "password-entries", "quit"],
+pub fn render_locked<'a>(theme: &Theme) -> Block<'a>{
+    Block::default()
+        .borders(Borders::ALL)
+        .style(theme.text)
+        .title("Locked")
+        .border_type(BorderType::Plain)
+}
 
+pub fn render_unlock<'a>(app: &App, keymap: &Keymap, area: Rect, theme: &Theme) -> Paragraph<'a>{
+    let title = match &app.input_error {
+        Some(err) => Span::styled(format!("Locked - {}", err), theme.error),
+        None => Span::raw(format!("Locked - enter the password and press {}, {} quits", key_hint(keymap, Action::Confirm),
+                                  key_hint(keymap, Action::Cancel))),
     };
+    Paragraph::new(render_editing_text(app, &true, area, theme))
+    .alignment(Alignment::Center)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(theme.text)
+            .title(title)
+            .border_type(BorderType::Plain),
+    )
+}
+
+pub fn render_save_as<'a>(app: &App, keymap: &Keymap, area: Rect, theme: &Theme) -> Paragraph<'a>{
+    Paragraph::new(render_editing_text(app, &false, area, theme))
+    .alignment(Alignment::Center)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(theme.text)
+            .title(format!("Save as - {} saves, {} cancels", key_hint(keymap, Action::Confirm), key_hint(keymap, Action::Cancel)))
+            .border_type(BorderType::Plain),
+    )
+}
+
+/// `editor` is the text being edited, its menu only shows how to leave it
+pub fn render_tabs<'a>(active_menu_item: MenuItem, editor: Option<&TextArea>, read_only: bool, keymap: &Keymap, theme: &Theme) -> Tabs<'a>{
+    let actions = menu_actions(active_menu_item, read_only, keymap);
+    let selected = actions.iter().position(|x| Some(*x) == active_menu_item.action());
+    let items = match editor {
+        Some(editor) => {
+            let input_actions: &[Action] = if editor.is_multiline() {
+                &[Action::Confirm, Action::Cancel, Action::Newline]
+            } else {
+                &[Action::Confirm, Action::Cancel]
+            };
+            input_actions.iter().map(|x| menu_item(&keymap.key(*x).unwrap_or_default(), x.name(), theme)).collect()
+        }
+        None => get_menu_for_mode(&actions, keymap, theme),
+    };
+    Tabs::new(items)
+    .select(selected.filter(|_| editor.is_none()).unwrap_or(usize::MAX))
+    .block(Block::default().title("Menu").borders(Borders::ALL))
+    .style(theme.text)
+    .highlight_style(theme.accent)
+    .divider(Span::raw("|"))
+}
+
+/// Actions of the tab menu which have a key, the ones changing the vault are hidden when it is opened read-only
+fn menu_actions(active_menu_item: MenuItem, read_only: bool, keymap: &Keymap) -> Vec<Action> {
+    Action::ALL
+        .iter()
+        .copied()
+        .filter(|x| x.in_menu() && x.available_in(active_menu_item))
+        .filter(|x| !read_only || !x.changes_vault())
+        .filter(|x| keymap.key(*x).is_some())
+        .collect()
+}
 
-    return men
+fn get_menu_for_mode<'a>(actions: &[Action], keymap: &Keymap, theme: &Theme) -> Vec<Spans<'a>> {
+    actions
+        .iter()
+        .map(|x| menu_item(&keymap.key(*x).unwrap_or_default(), x.name(), theme))
+        .collect()
+}
+
+/// First key of the action for texts like "press Enter", `unbound` if it has none
+pub fn key_hint(keymap: &Keymap, action: Action) -> String {
+    keymap.key(action).unwrap_or_else(|| String::from("unbound"))
+}
+
+/// Headings of the help with the actions only available there
+const HELP_SECTIONS: [(&str, Option<MenuItem>); 6] = [
+    ("Everywhere", None),
+    ("Password entries", Some(MenuItem::PasswordEntries)),
+    ("Selected entry", Some(MenuItem::SelctedEntry)),
+    ("Recycle bin", Some(MenuItem::RecycleBin)),
+    ("Dialogs", None),
+    ("Input fields", None),
+];
+
+/// Every action with its keys by menu, for dialogs and for input fields, the section of the current menu is highlighted
+pub fn help_text<'a>(keymap: &Keymap, active_menu_item: MenuItem, read_only: bool, theme: &Theme) -> Vec<Spans<'a>> {
+    let everywhere = |x: &Action| [MenuItem::Home, MenuItem::PasswordEntries, MenuItem::SelctedEntry, MenuItem::RecycleBin]
+        .iter()
+        .all(|menu| x.available_in(*menu));
+    let mut text = Vec::new();
+    for (index, (heading, menu)) in HELP_SECTIONS.iter().enumerate() {
+        let actions: Vec<Action> = Action::ALL
             .iter()
-            .map(|t| {
-                let (first, rest) = t.split_at(1);
-                Spans::from(vec![
-                    Span::styled(
-                        first,
-                        Style::default()
-                            .fg(Color::Yellow)
-                            .add_modifier(Modifier::UNDERLINED),
-                        ),
-                    Span::styled(rest, Style::default().fg(Color::White)),
-                    ])
-                })
-                .collect();
+            .copied()
+            .filter(|x| match (index, menu) {
+                (0, _) => everywhere(x),
+                (_, Some(menu)) => x.available_in(*menu) && !everywhere(x),
+                (4, _) => x.is_dialog(),
+                _ => x.is_input(),
+            })
+            .collect();
+        if !text.is_empty() {
+            text.push(Spans::from(""));
+        }
+        let style = if *menu == Some(active_menu_item) { theme.accent } else { theme.title };
+        text.push(Spans::from(Span::styled(heading.to_string(), style)));
+        for action in actions {
+            let keys: Vec<String> = keymap.keys(action).iter().map(|x| x.to_string()).collect();
+            let keys = if keys.is_empty() { String::from("unbound") } else { keys.join(", ") };
+            let note = if read_only && action.changes_vault() { " (read-only)" } else { "" };
+            text.push(Spans::from(vec![
+                Span::styled(format!("{:>18}", keys), theme.key),
+                Span::styled(format!("  {}{}", action.description(), note), theme.text),
+            ]));
+        }
+    }
+    text.push(Spans::from(Span::styled(format!("{:>18}  select while moving the cursor", "shift"), theme.text)));
+    text.push(Spans::from(Span::styled(format!("{:>18}  type them", "other keys"), theme.text)));
+    text
+}
+
+pub fn render_help<'a>(text: Vec<Spans<'a>>, scroll: u16, keymap: &Keymap, theme: &Theme) -> Paragraph<'a> {
+    Paragraph::new(text)
+    .scroll((scroll, 0))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(theme.text)
+            .title(format!("Keys - {}/{} scroll, any other key closes", key_hint(keymap, Action::Up), key_hint(keymap, Action::Down)))
+            .border_type(BorderType::Plain),
+    )
+}
+
+fn menu_item<'a>(key: &str, name: &str, theme: &Theme) -> Spans<'a> {
+    Spans::from(vec![
+        Span::styled(
+            key.to_owned(),
+            theme.key,
+            ),
+        Span::styled(format!(" {}", name), theme.text),
+        ])
 }
 
 pub fn render_chunks(size: Rect) -> Vec<Rect>{
-    return Layout::default()
+    Layout::default()
                 .direction(Direction::Vertical)
                 .margin(2)
                 .constraints(
@@ -193,11 +476,12 @@ pub fn render_chunks(size: Rect) -> Vec<Rect>{
                     ]
                     .as_ref(),
                 )
-                .split(size);
+                .split(size)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn display_selected_entry(db: &DatabaseFile, app: &App, rect: &mut Frame<CrosstermBackend<Stdout>>,
-                              detail_list_state: &mut ListState, selected_entry: usize, chunks: &[Rect], show_value: &bool) -> usize{
+                              detail_list_state: &mut ListState, selected_entry: usize, chunks: &[Rect], show_value: &bool, theme: &Theme) -> usize{
     let entry_chunks = Layout::default()
                         .direction(Direction::Horizontal)
                         .constraints([Constraint::Percentage(20), Constraint::Percentage(80)].as_ref(),)
@@ -207,104 +491,94 @@ pub fn display_selected_entry(db: &DatabaseFile, app: &App, rect: &mut Frame<Cro
                             [Constraint::Percentage(20), Constraint::Percentage(80)].as_ref(),)
                         .split(entry_chunks[1]);
 
-    let (left, bottom_right, top_right, attribute_count) = render_selected_entry(selected_entry, detail_list_state, app, db, show_value);
+    let (left, bottom_right, top_right, attribute_count) = render_selected_entry(selected_entry, detail_list_state, app, db, show_value,
+                                                                                 &right_chunks, theme);
     rect.render_stateful_widget(left, entry_chunks[0], detail_list_state);
     rect.render_widget(bottom_right, right_chunks[1]);
     rect.render_widget(top_right, right_chunks[0]);
     attribute_count
 }
 
-fn render_selected_entry<'a>(index: usize, detail_list_state: &ListState, app: &'a App, db: &DatabaseFile, show_value: &bool)
-                                -> (List<'a>, Paragraph<'a>, Paragraph<'a>, usize){
-    let props = Block::default()
-        .borders(Borders::ALL)
-        .style(Style::default().fg(Color::White))
-        .title("Changeable Properties")
-        .border_type(BorderType::Plain);
-
+/// `areas` are the input field and the detail pane, multiline fields are edited in the detail pane
+#[allow(clippy::too_many_arguments)]
+fn render_selected_entry<'a>(index: usize, detail_list_state: &ListState, app: &'a App, db: &DatabaseFile, show_value: &bool,
+                             areas: &[Rect], theme: &Theme) -> (List<'a>, Paragraph<'a>, Paragraph<'a>, usize){
     let default = PasswordEntry{id: String::from("1"), title: String::from("Empty"), value: String::from("Empty"),
                         name: String::from("Empty"), url: String::from("Empty"), comment: String::from("Empty"),
                         entry_type: EntryType::ClassicPassword, last_modified: Local::now().to_string(),
-                        files: Vec::new()};
+                        files: Vec::new(), extra_fields: Vec::new(), group: String::new()};
     let selected_entry = db.entries.get(index).unwrap_or(&default).clone();
 
-    let names: Vec<String> = ["Title".into(), "Name".into(), "Value".into(), "Url".into(), "Comment".into()].to_vec();
+    let props = Block::default()
+        .borders(Borders::ALL)
+        .style(theme.text)
+        .title(format!("{} Properties", selected_entry.entry_type.label()))
+        .border_type(BorderType::Plain);
+
+    let fields = selected_entry.fields();
 
-    let items: Vec<_> = names
+    let items: Vec<_> = fields
         .iter()
-        .map(|name| {
+        .map(|field| {
             ListItem::new(Spans::from(vec![Span::styled(
-                name.clone(),
+                field.label,
                 Style::default(),
             )]))
         })
         .collect();
 
     let list = List::new(items).block(props).highlight_style(
-        Style::default()
-            .bg(Color::Yellow)
-            .fg(Color::Black)
-            .add_modifier(Modifier::BOLD),
+        theme.field_selection,
     );
 
-    let mut value = match detail_list_state.selected().unwrap() {
-        0 => selected_entry.title.clone(),
-        1 => selected_entry.name.clone(),
-        2 => selected_entry.value.clone(),
-        3 => selected_entry.url.clone(),
-        4 => selected_entry.comment.clone(),
-        _ => "Error".to_owned()
+    let selected_field = fields.get(detail_list_state.selected().unwrap_or(0));
+    let value = match selected_field {
+        Some(field) => selected_entry.get_field(field),
+        None => "Error".to_owned()
     };
 
-    let show_value_expression = !*show_value && detail_list_state.selected().unwrap() == 2;
-    value = display_password_correctly(value, &show_value_expression);
-    let detail = Paragraph::new(vec![
-        Spans::from(vec![Span::raw(value)]),
-    ]);
-
-    let text = render_editing_text(app, &show_value_expression);
+    let show_value_expression = !*show_value && selected_field.map(|x| x.secret).unwrap_or(false);
+    let lines: Vec<String> = if selected_field.map(|x| x.multiline).unwrap_or(false) {
+        value.lines().map(|line| line.to_owned()).collect()
+    } else {
+        vec![value]
+    };
+    let editing = matches!(app.input_mode, InputMode::Editing);
+    let (detail, text) = if editing && app.editor.is_multiline() {
+        let detail = Paragraph::new(render_editing_text(app, &show_value_expression, areas[1], theme))
+            .block(Block::default().title("Editing").borders(Borders::ALL));
+        (detail, Vec::new())
+    } else {
+        let detail = Paragraph::new(lines
+                .into_iter()
+                .map(|line| Spans::from(vec![Span::raw(display_password_correctly(line, &show_value_expression))]))
+                .collect::<Vec<_>>())
+            .wrap(Wrap { trim: false });
+        let text = if editing { render_editing_text(app, &show_value_expression, areas[0], theme) } else { Vec::new() };
+        (detail, text)
+    };
 
+    let input_title = match &app.input_error {
+        Some(err) => Span::styled(format!("Input-Field - {}", err), theme.error),
+        None => Span::raw("Input-Field"),
+    };
     let input_field = Paragraph::new(text)
-        .block(Block::default().title("Input-Field").borders(Borders::ALL))
-        .style(Style::default().fg(Color::White))
+        .block(Block::default().title(input_title).borders(Borders::ALL))
+        .style(theme.text)
         .alignment(Alignment::Center);
 
-    (list, detail, input_field, names.len()) 
+    (list, detail, input_field, fields.len()) 
 }
 
-fn render_editing_text<'a>(app: &App, show_value_expression: &bool) -> Vec<Spans<'a>>{
-    let mut text = vec![];
-    if !app.input.clone().is_empty(){
-        let mut left_side = app.input[..app.input_index-1].to_string(); 
-        left_side = display_password_correctly(left_side, show_value_expression);
-        if app.input.len() > app.input_index-1{ 
-            let right_side = display_password_correctly(app.input[app.input_index..].to_string(), show_value_expression);
-            let middle = display_password_correctly(app.input.chars().nth(app.input_index-1).unwrap_or_default().to_string(),
-                            show_value_expression); 
-            text = vec![
-                Spans::from(vec![
-                    Span::styled(left_side, Style::default().fg(Color::Blue)), //before index
-                    Span::styled(middle, 
-                                 Style::default().add_modifier(Modifier::BOLD)), // at index
-                    Span::styled(right_side, Style::default().fg(Color::Blue))
-                ])
-            ];
-
-        } else{
-            text = vec![
-                Spans::from(vec![
-                    Span::styled(left_side, Style::default().fg(Color::Blue)), //before index
-                    Span::styled("_",Style::default().add_modifier(Modifier::SLOW_BLINK)), // at index
-                ])
-            ];
-        }
-    }
-    text
+/// The text of the input field inside the borders of `area`
+fn render_editing_text<'a>(app: &App, show_value_expression: &bool, area: Rect, theme: &Theme) -> Vec<Spans<'a>>{
+    let styles = TextStyles { text: theme.input, cursor: theme.cursor, selection: theme.selection };
+    app.editor.render(area.width.saturating_sub(2), area.height.saturating_sub(2), *show_value_expression, &styles)
 }
 
 fn display_password_correctly(value: String, show_value_expression: &bool) -> String{ 
     if *show_value_expression{
-        return (0..value.len()).map(|_| "*").collect::<String>(); 
+        return masked(&value);
     }
     value
 }
@@ -1,33 +1,89 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::database::lock::VaultLock;
+use crate::database::structures::DatabaseFile;
 use super::enums::*;
+use super::keymap::Action;
+use super::dialog::{Dialog, Notice};
+use super::text_area::TextArea;
 
 pub struct App {
-    /// Current value of the input box
-    pub input: String,
+    /// Text of the input field with its cursor
+    pub editor: TextArea,
     /// Current input mode
     pub input_mode: InputMode,
     /// History of recorded messages
     pub message: Vec<String>,
-    /// Current index place from messages
-    pub input_index: usize
+    /// Confirmation or error in front of everything else
+    pub dialog: Option<Dialog>,
+    /// Reason why the last input was rejected
+    pub input_error: Option<String>,
+    /// Result of the last action, shown in the info bar for a while
+    pub notice: Option<Notice>,
+    /// Value copied to the clipboard and when, it is removed again after the clipboard timeout
+    pub copied: Option<(String, Instant)>,
+    /// Time of the last key press, the vault is locked after the lock timeout
+    pub last_input: Instant,
+    /// Scroll position of the help, None while it is closed
+    pub help: Option<u16>
 }
 
 impl Default for App {
     fn default() -> App {
         App {
-            input: String::new(),
+            editor: TextArea::default(),
             input_mode: InputMode::Navigation,
             message: Vec::new(),
-            input_index: 0 // 1 is the first char
+            dialog: None,
+            input_error: None,
+            notice: None,
+            copied: None,
+            last_input: Instant::now(),
+            help: None
+        }
+    }
+}
+
+impl MenuItem {
+    /// The action leading to the menu, highlighted in the tab menu
+    pub fn action(&self) -> Option<Action> {
+        match self {
+            MenuItem::Home => Some(Action::Home),
+            MenuItem::PasswordEntries => Some(Action::PasswordEntries),
+            MenuItem::SelctedEntry => None,
+            MenuItem::RecycleBin => Some(Action::RecycleBin),
         }
     }
 }
 
-impl From<MenuItem> for usize {
-    fn from(input: MenuItem) -> usize {
-        match input {
-            MenuItem::Home => 0,
-            MenuItem::PasswordEntries => 1,
-            MenuItem::SelctedEntry => 2,
+/// The file the GUI saves to and the state of the last save
+pub struct Session {
+    pub path: PathBuf,
+    /// Vault like it was loaded or saved last, the base when merging changes of other programs
+    pub saved: DatabaseFile,
+    /// Another process has the vault open, nothing can be saved
+    pub read_only: bool,
+    /// Lock of `path`, None when opened read-only
+    pub lock: Option<VaultLock>,
+    /// Saves changes automatically after this interval
+    pub autosave: Option<Duration>,
+    pub last_save: Instant,
+}
+
+impl Session {
+    pub fn new(path: PathBuf, db: &DatabaseFile, lock: Option<VaultLock>, autosave: Option<Duration>) -> Session {
+        Session {
+            path,
+            saved: db.clone(),
+            read_only: lock.is_none(),
+            lock,
+            autosave,
+            last_save: Instant::now(),
         }
     }
-}
\ No newline at end of file
+
+    pub fn is_modified(&self, db: &DatabaseFile) -> bool {
+        !db.same_content(&self.saved)
+    }
+}
@@ -0,0 +1,67 @@
+use crossterm::{
+    cursor::{MoveTo, Show},
+    event::{DisableBracketedPaste, EnableBracketedPaste},
+    execute,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+use std::io::stdout;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+/// Whether the GUI owns the terminal, the panic hook has no access to the guard
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static ALTERNATE_SCREEN: AtomicBool = AtomicBool::new(false);
+static PANIC_HOOK: Once = Once::new();
+
+/// Puts the terminal into raw mode with bracketed paste for the GUI and gives it back in its old state when dropped,
+/// on errors and panics as well
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn enter(alternate_screen: bool) -> Result<TerminalGuard, anyhow::Error> {
+        PANIC_HOOK.call_once(|| {
+            let previous = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                restore();
+                previous(info);
+            }));
+        });
+        enable_raw_mode()?;
+        ACTIVE.store(true, Ordering::SeqCst);
+        let guard = TerminalGuard;
+        // pasted text arrives as a whole instead of as keys, line breaks would confirm the input otherwise
+        execute!(stdout(), EnableBracketedPaste)?;
+        if alternate_screen {
+            execute!(stdout(), EnterAlternateScreen)?;
+            ALTERNATE_SCREEN.store(true, Ordering::SeqCst);
+        }
+        std::result::Result::Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Wipes what the GUI showed so no secret stays visible and restores the terminal. On the normal screen
+/// the scrollback is wiped as well, the alternate screen never gets there. Only the first call does anything
+fn restore() {
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    let mut out = stdout();
+    let _ = execute!(out, Clear(ClearType::All), MoveTo(0, 0));
+    if ALTERNATE_SCREEN.swap(false, Ordering::SeqCst) {
+        let _ = execute!(out, LeaveAlternateScreen);
+    } else {
+        let _ = execute!(out, Clear(ClearType::Purge));
+    }
+    let _ = execute!(out, DisableBracketedPaste, Show);
+    let _ = disable_raw_mode();
+}
@@ -0,0 +1,600 @@
+use std::cell::Cell;
+use tui::style::{Modifier, Style};
+use tui::text::{Span, Spans};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Place in the text, `column` is a byte offset into the line and always at the start of a grapheme cluster,
+/// so characters built from several code points like emoji or accents are edited as one
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Movement {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordLeft,
+    WordRight,
+    LineStart,
+    LineEnd,
+}
+
+/// Styles of the text, the cursor and the selected text
+pub struct TextStyles {
+    pub text: Style,
+    pub cursor: Style,
+    pub selection: Style,
+}
+
+/// Text of an input field with a cursor and a selection. Single line fields turn line breaks into spaces
+pub struct TextArea {
+    /// Never empty, an empty text is one empty line
+    lines: Vec<String>,
+    cursor: Position,
+    /// Other end of the selection, the cursor is the moving end
+    anchor: Option<Position>,
+    multiline: bool,
+    /// First shown row, it follows the cursor while rendering
+    scroll: Cell<usize>,
+}
+
+impl Default for TextArea {
+    fn default() -> TextArea {
+        TextArea::new("", false)
+    }
+}
+
+impl TextArea {
+    /// The cursor is placed at the end of the text
+    pub fn new(text: &str, multiline: bool) -> TextArea {
+        let mut area = TextArea {
+            lines: vec![String::new()],
+            cursor: Position::default(),
+            anchor: None,
+            multiline,
+            scroll: Cell::new(0),
+        };
+        area.insert_str(text);
+        area
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn is_multiline(&self) -> bool {
+        self.multiline
+    }
+
+    pub fn clear(&mut self) {
+        *self = TextArea::new("", self.multiline);
+    }
+
+    /// Replaces the selection if there is one
+    pub fn insert_char(&mut self, c: char) {
+        if c == '\n' {
+            self.newline();
+            return;
+        }
+        self.delete_selection();
+        let Position { line, column } = self.cursor;
+        self.lines[line].insert(column, c);
+        self.cursor.column += c.len_utf8();
+        self.snap_cursor();
+    }
+
+    /// Inserts typed or pasted text at the cursor and replaces the selection
+    pub fn insert_str(&mut self, text: &str) {
+        self.delete_selection();
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let text = if self.multiline {
+            text
+        } else {
+            text.replace('\n', " ")
+        };
+        let Position { line, column } = self.cursor;
+        let rest = self.lines[line].split_off(column);
+        let mut current = line;
+        for (index, part) in text.split('\n').enumerate() {
+            if index == 0 {
+                self.lines[line].push_str(part);
+            } else {
+                current += 1;
+                self.lines.insert(current, part.to_owned());
+            }
+        }
+        self.cursor = Position {
+            line: current,
+            column: self.lines[current].len(),
+        };
+        self.lines[current].push_str(&rest);
+        self.snap_cursor();
+    }
+
+    /// Splits the line at the cursor, single line fields stay as they are
+    pub fn newline(&mut self) {
+        if self.multiline {
+            self.insert_str("\n");
+        }
+    }
+
+    /// Deletes the selection or the character before the cursor, at the start of a line it joins the line
+    /// with the previous one
+    pub fn delete_back(&mut self) {
+        if self.delete_selection() {
+            return;
+        }
+        let Position { line, column } = self.cursor;
+        if column > 0 {
+            let start = self.previous_boundary(line, column);
+            self.lines[line].replace_range(start..column, "");
+            self.cursor.column = start;
+        } else if line > 0 {
+            let removed = self.lines.remove(line);
+            self.cursor = Position {
+                line: line - 1,
+                column: self.lines[line - 1].len(),
+            };
+            self.lines[line - 1].push_str(&removed);
+        }
+    }
+
+    /// Deletes the selection or the character after the cursor
+    pub fn delete_forward(&mut self) {
+        if self.delete_selection() {
+            return;
+        }
+        let Position { line, column } = self.cursor;
+        if column < self.lines[line].len() {
+            let end = self.next_boundary(line, column);
+            self.lines[line].replace_range(column..end, "");
+        } else if line + 1 < self.lines.len() {
+            let next = self.lines.remove(line + 1);
+            self.lines[line].push_str(&next);
+        }
+    }
+
+    /// Moves the cursor, `select` extends the selection instead of dropping it
+    pub fn move_cursor(&mut self, movement: Movement, select: bool) {
+        if select {
+            if self.anchor.is_none() {
+                self.anchor = Some(self.cursor);
+            }
+        } else if let Some((start, end)) = self.selection() {
+            self.anchor = None;
+            // left and right only leave the selection at its side
+            match movement {
+                Movement::Left => return self.cursor = start,
+                Movement::Right => return self.cursor = end,
+                _ => {}
+            }
+        } else {
+            self.anchor = None;
+        }
+        let Position { line, column } = self.cursor;
+        let length = self.lines[line].len();
+        self.cursor = match movement {
+            Movement::Left if column > 0 => Position {
+                line,
+                column: self.previous_boundary(line, column),
+            },
+            Movement::Left if line > 0 => Position {
+                line: line - 1,
+                column: self.lines[line - 1].len(),
+            },
+            Movement::Right if column < length => Position {
+                line,
+                column: self.next_boundary(line, column),
+            },
+            Movement::Right if line + 1 < self.lines.len() => Position {
+                line: line + 1,
+                column: 0,
+            },
+            Movement::Up if line > 0 => Position {
+                line: line - 1,
+                column: self.column_at(line - 1, self.width_before(line, column)),
+            },
+            Movement::Down if line + 1 < self.lines.len() => Position {
+                line: line + 1,
+                column: self.column_at(line + 1, self.width_before(line, column)),
+            },
+            Movement::Up | Movement::LineStart => Position { line, column: 0 },
+            Movement::Down | Movement::LineEnd => Position {
+                line,
+                column: length,
+            },
+            Movement::WordLeft => self.word_left(),
+            Movement::WordRight => self.word_right(),
+            _ => self.cursor,
+        };
+        if self.anchor == Some(self.cursor) {
+            self.anchor = None;
+        }
+    }
+
+    pub fn select_all(&mut self) {
+        let last = self.lines.len() - 1;
+        self.anchor = Some(Position::default());
+        self.cursor = Position {
+            line: last,
+            column: self.lines[last].len(),
+        };
+        if self.anchor == Some(self.cursor) {
+            self.anchor = None;
+        }
+    }
+
+    /// Start and end of the selection, None if nothing is selected
+    pub fn selection(&self) -> Option<(Position, Position)> {
+        let anchor = self.anchor.filter(|x| *x != self.cursor)?;
+        Some((anchor.min(self.cursor), anchor.max(self.cursor)))
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        if start.line == end.line {
+            return Some(self.lines[start.line][start.column..end.column].to_owned());
+        }
+        let mut parts = vec![&self.lines[start.line][start.column..]];
+        parts.extend(
+            self.lines[start.line + 1..end.line]
+                .iter()
+                .map(|x| x.as_str()),
+        );
+        parts.push(&self.lines[end.line][..end.column]);
+        Some(parts.join("\n"))
+    }
+
+    /// Removes the selected text, false if nothing was selected
+    pub fn delete_selection(&mut self) -> bool {
+        let (start, end) = match self.selection() {
+            Some(selection) => selection,
+            None => return false,
+        };
+        let rest = self.lines[end.line][end.column..].to_owned();
+        self.lines.drain(start.line + 1..=end.line);
+        self.lines[start.line].truncate(start.column);
+        self.lines[start.line].push_str(&rest);
+        self.cursor = start;
+        self.anchor = None;
+        true
+    }
+
+    /// The rows shown in an area of `width` and `height`. Lines are wrapped and the rows are scrolled so the
+    /// cursor stays visible. Rows are filled up to the display width, wide characters like CJK or emoji take two
+    /// columns. `mask` hides every character behind a '*'
+    pub fn render<'a>(
+        &self,
+        width: u16,
+        height: u16,
+        mask: bool,
+        styles: &TextStyles,
+    ) -> Vec<Spans<'a>> {
+        let width = usize::from(width.max(1));
+        let height = usize::from(height.max(1));
+        let selection = self.selection();
+        let selected = |position: Position| {
+            selection
+                .map(|(start, end)| start <= position && position < end)
+                .unwrap_or(false)
+        };
+        let mut rows: Vec<Vec<(String, Style)>> = Vec::new();
+        let mut cursor_row = 0;
+        for (line, text) in self.lines.iter().enumerate() {
+            let mut row = Vec::new();
+            let mut row_width = 0;
+            let cells = text
+                .grapheme_indices(true)
+                .map(|(column, grapheme)| (Position { line, column }, grapheme))
+                .chain(Some((
+                    Position {
+                        line,
+                        column: text.len(),
+                    },
+                    "\n",
+                )));
+            for (position, grapheme) in cells {
+                let style = if position == self.cursor {
+                    styles.cursor
+                } else if selected(position) {
+                    styles.selection
+                } else {
+                    styles.text
+                };
+                let shown = match grapheme {
+                    // the end of the line only takes space for the cursor or a selected line break
+                    "\n" if position == self.cursor => "_",
+                    "\n" if selected(position) => " ",
+                    "\n" => continue,
+                    _ if mask => "*",
+                    "\t" => " ",
+                    grapheme => grapheme,
+                };
+                let shown_width = shown.width();
+                if row_width + shown_width > width && !row.is_empty() {
+                    rows.push(std::mem::take(&mut row));
+                    row_width = 0;
+                }
+                if position == self.cursor {
+                    cursor_row = rows.len();
+                }
+                let style = if grapheme == "\n" && position == self.cursor {
+                    style.add_modifier(Modifier::SLOW_BLINK)
+                } else {
+                    style
+                };
+                row.push((shown.to_owned(), style));
+                row_width += shown_width;
+            }
+            rows.push(row);
+        }
+
+        let mut scroll = self.scroll.get().min(rows.len().saturating_sub(1));
+        if cursor_row < scroll {
+            scroll = cursor_row;
+        } else if cursor_row >= scroll + height {
+            scroll = cursor_row + 1 - height;
+        }
+        self.scroll.set(scroll);
+
+        rows.into_iter()
+            .skip(scroll)
+            .take(height)
+            .map(|row| {
+                let mut spans: Vec<Span> = Vec::new();
+                for (grapheme, style) in row {
+                    match spans.last_mut() {
+                        Some(span) if span.style == style => {
+                            span.content.to_mut().push_str(&grapheme)
+                        }
+                        _ => spans.push(Span::styled(grapheme, style)),
+                    }
+                }
+                Spans::from(spans)
+            })
+            .collect()
+    }
+
+    fn previous_boundary(&self, line: usize, column: usize) -> usize {
+        self.lines[line][..column]
+            .grapheme_indices(true)
+            .next_back()
+            .map(|(index, _)| index)
+            .unwrap_or(0)
+    }
+
+    fn next_boundary(&self, line: usize, column: usize) -> usize {
+        column
+            + self.lines[line][column..]
+                .graphemes(true)
+                .next()
+                .map(|x| x.len())
+                .unwrap_or(0)
+    }
+
+    /// Moves the cursor behind the grapheme cluster it ended up in, e.g. after a joiner merged two emoji
+    fn snap_cursor(&mut self) {
+        let Position { line, column } = self.cursor;
+        let inside = self.lines[line]
+            .grapheme_indices(true)
+            .find(|(start, grapheme)| start + grapheme.len() > column)
+            .filter(|(start, _)| *start < column);
+        if let Some((start, grapheme)) = inside {
+            self.cursor.column = start + grapheme.len();
+        }
+    }
+
+    /// Display width of the line up to the cursor, keeps the column when moving up and down
+    fn width_before(&self, line: usize, column: usize) -> usize {
+        self.lines[line][..column].width()
+    }
+
+    /// Offset of the last grapheme cluster of the line which starts at most `width` columns in
+    fn column_at(&self, line: usize, width: usize) -> usize {
+        let mut shown = 0;
+        for (column, grapheme) in self.lines[line].grapheme_indices(true) {
+            shown += grapheme.width();
+            if shown > width {
+                return column;
+            }
+        }
+        self.lines[line].len()
+    }
+
+    /// Start of the word before the cursor, the end of the previous line at the start of a line
+    fn word_left(&self) -> Position {
+        let Position { line, column } = self.cursor;
+        if column == 0 {
+            return match line {
+                0 => self.cursor,
+                _ => Position {
+                    line: line - 1,
+                    column: self.lines[line - 1].len(),
+                },
+            };
+        }
+        let mut start = column;
+        let mut seen_word = false;
+        for (index, grapheme) in self.lines[line][..column].grapheme_indices(true).rev() {
+            if is_word(grapheme) {
+                seen_word = true;
+            } else if seen_word {
+                break;
+            }
+            start = index;
+        }
+        Position {
+            line,
+            column: start,
+        }
+    }
+
+    /// End of the word after the cursor, the start of the next line at the end of a line
+    fn word_right(&self) -> Position {
+        let Position { line, column } = self.cursor;
+        let text = &self.lines[line][column..];
+        if text.is_empty() {
+            return match line + 1 < self.lines.len() {
+                true => Position {
+                    line: line + 1,
+                    column: 0,
+                },
+                false => self.cursor,
+            };
+        }
+        let mut end = text.len();
+        let mut seen_word = false;
+        for (index, grapheme) in text.grapheme_indices(true) {
+            if is_word(grapheme) {
+                seen_word = true;
+            } else if seen_word {
+                end = index;
+                break;
+            }
+        }
+        Position {
+            line,
+            column: column + end,
+        }
+    }
+}
+
+/// One '*' for every character the user sees, however many bytes or code points it has
+pub fn masked(text: &str) -> String {
+    text.graphemes(true).map(|_| '*').collect()
+}
+
+/// Letters of every script, digits and underscores, judged by the base character of the cluster
+fn is_word(grapheme: &str) -> bool {
+    grapheme
+        .chars()
+        .next()
+        .map(|c| c.is_alphanumeric() || c == '_')
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str, multiline: bool) -> TextArea {
+        let mut area = TextArea::new("", multiline);
+        for c in text.chars() {
+            area.insert_char(c);
+        }
+        area
+    }
+
+    fn rows(area: &TextArea, width: u16, height: u16, mask: bool) -> Vec<String> {
+        let styles = TextStyles {
+            text: Style::default(),
+            cursor: Style::default(),
+            selection: Style::default(),
+        };
+        area.render(width, height, mask, &styles)
+            .iter()
+            .map(|row| row.0.iter().map(|x| x.content.as_ref()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn edits_umlauts_in_the_middle() {
+        let mut area = typed("Grüße", false);
+        area.move_cursor(Movement::Left, false);
+        area.move_cursor(Movement::Left, false);
+        area.delete_back();
+        area.insert_char('ö');
+        area.insert_char('ß');
+        assert_eq!(area.text(), "Größße");
+        area.delete_forward();
+        assert_eq!(area.text(), "Größe");
+    }
+
+    #[test]
+    fn emoji_sequences_are_one_character() {
+        let mut area = TextArea::new("hi 👨‍👩‍👧 👍🏽", false);
+        area.delete_back();
+        assert_eq!(area.text(), "hi 👨‍👩‍👧 ");
+        area.move_cursor(Movement::Left, false);
+        area.move_cursor(Movement::Left, false);
+        area.insert_char('x');
+        assert_eq!(area.text(), "hi x👨‍👩‍👧 ");
+    }
+
+    #[test]
+    fn typed_joiners_and_accents_combine() {
+        let mut area = typed("👩\u{200d}👧 e\u{301}", false);
+        area.delete_back();
+        assert_eq!(area.text(), "👩\u{200d}👧 ");
+        area.delete_back();
+        area.delete_back();
+        assert_eq!(area.text(), "");
+    }
+
+    #[test]
+    fn moves_by_words_in_any_script() {
+        let mut area = TextArea::new("naïve café, привет мир", false);
+        area.move_cursor(Movement::WordLeft, false);
+        area.insert_char('_');
+        assert_eq!(area.text(), "naïve café, привет _мир");
+        area.move_cursor(Movement::LineStart, false);
+        area.move_cursor(Movement::WordRight, false);
+        area.move_cursor(Movement::WordRight, true);
+        assert_eq!(area.selected_text().as_deref(), Some(" café"));
+    }
+
+    #[test]
+    fn keeps_the_display_column_between_lines() {
+        let mut area = TextArea::new("日本語\nabcdef", true);
+        area.move_cursor(Movement::Up, false);
+        area.move_cursor(Movement::LineStart, false);
+        area.move_cursor(Movement::Right, false);
+        area.move_cursor(Movement::Right, false);
+        area.move_cursor(Movement::Down, false);
+        area.insert_char('|');
+        assert_eq!(area.text(), "日本語\nabcd|ef");
+        area.move_cursor(Movement::Up, false);
+        area.insert_char('|');
+        assert_eq!(area.text(), "日本|語\nabcd|ef");
+    }
+
+    #[test]
+    fn deletes_selections_over_several_lines() {
+        let mut area = TextArea::new("Ärger\nüber\nÖl", true);
+        area.move_cursor(Movement::Up, true);
+        area.move_cursor(Movement::Up, true);
+        assert_eq!(area.selected_text().as_deref(), Some("ger\nüber\nÖl"));
+        area.delete_back();
+        assert_eq!(area.text(), "Är");
+        area.select_all();
+        area.insert_str("Straße\r\nnächste");
+        assert_eq!(area.text(), "Straße\nnächste");
+    }
+
+    #[test]
+    fn pasted_line_breaks_become_spaces_in_single_lines() {
+        let mut area = TextArea::new("", false);
+        area.insert_str("ein\nzwei\r\ndrei");
+        area.newline();
+        assert_eq!(area.text(), "ein zwei drei");
+    }
+
+    #[test]
+    fn wraps_by_display_width() {
+        let area = TextArea::new("日本語abc", false);
+        assert_eq!(rows(&area, 4, 5, false), vec!["日本", "語ab", "c_"]);
+        assert_eq!(rows(&area, 4, 5, true), vec!["****", "**_"]);
+        assert_eq!(rows(&area, 4, 1, false), vec!["c_"]);
+    }
+
+    #[test]
+    fn masks_every_visible_character_once() {
+        assert_eq!(masked("ä👍🏽e\u{301}日"), "****");
+        assert_eq!(masked(""), "");
+    }
+}
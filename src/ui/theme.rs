@@ -0,0 +1,272 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::env;
+use tui::style::{Color, Modifier, Style};
+
+use crate::utils::settings::Settings;
+
+/// Styles of all parts of the GUI
+#[derive(Clone, Debug)]
+pub struct Theme {
+    /// Borders and plain text
+    pub text: Style,
+    pub title: Style,
+    /// Active tab, answers of confirmations and the modified marker
+    pub accent: Style,
+    /// Keys in the tab menu
+    pub key: Style,
+    pub header: Style,
+    /// Selected entry in the lists
+    pub selection: Style,
+    /// Selected field of an entry
+    pub field_selection: Style,
+    pub status: Style,
+    pub warning: Style,
+    pub error: Style,
+    pub input: Style,
+    pub cursor: Style,
+}
+
+pub const BUILT_IN_THEMES: [&str; 4] = ["dark", "light", "high-contrast", "monochrome"];
+
+impl Theme {
+    pub fn dark() -> Theme {
+        Theme {
+            text: Style::default().fg(Color::White),
+            title: Style::default().fg(Color::LightBlue),
+            accent: Style::default().fg(Color::Yellow),
+            key: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::UNDERLINED),
+            header: Style::default().add_modifier(Modifier::BOLD),
+            selection: Style::default()
+                .bg(Color::Red)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            field_selection: Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            status: Style::default().fg(Color::LightCyan),
+            warning: Style::default().fg(Color::LightRed),
+            error: Style::default().fg(Color::Red),
+            input: Style::default().fg(Color::Blue),
+            cursor: Style::default().add_modifier(Modifier::BOLD),
+        }
+    }
+
+    pub fn light() -> Theme {
+        Theme {
+            text: Style::default().fg(Color::Black),
+            title: Style::default().fg(Color::Blue),
+            accent: Style::default().fg(Color::Magenta),
+            key: Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::UNDERLINED),
+            header: Style::default().add_modifier(Modifier::BOLD),
+            selection: Style::default()
+                .bg(Color::Blue)
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            field_selection: Style::default()
+                .bg(Color::Magenta)
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            status: Style::default().fg(Color::Cyan),
+            warning: Style::default().fg(Color::Red),
+            error: Style::default().fg(Color::Red),
+            input: Style::default().fg(Color::Blue),
+            cursor: Style::default().add_modifier(Modifier::BOLD),
+        }
+    }
+
+    pub fn high_contrast() -> Theme {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Theme {
+            text: Style::default().fg(Color::White),
+            title: bold.fg(Color::White),
+            accent: bold.fg(Color::LightYellow),
+            key: bold
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::UNDERLINED),
+            header: bold.add_modifier(Modifier::UNDERLINED),
+            selection: bold.bg(Color::White).fg(Color::Black),
+            field_selection: bold.bg(Color::LightYellow).fg(Color::Black),
+            status: Style::default().fg(Color::White),
+            warning: bold.fg(Color::LightRed),
+            error: bold.fg(Color::LightRed),
+            input: Style::default().fg(Color::LightCyan),
+            cursor: bold.add_modifier(Modifier::REVERSED),
+        }
+    }
+
+    /// Only bold, underlined and reversed text, used for `NO_COLOR` as well
+    pub fn monochrome() -> Theme {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Theme {
+            text: Style::default(),
+            title: bold,
+            accent: bold,
+            key: Style::default().add_modifier(Modifier::UNDERLINED),
+            header: bold,
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            field_selection: bold.add_modifier(Modifier::REVERSED),
+            status: Style::default(),
+            warning: bold,
+            error: bold,
+            input: Style::default(),
+            cursor: Style::default().add_modifier(Modifier::REVERSED),
+        }
+    }
+
+    fn built_in(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "monochrome" => Some(Theme::monochrome()),
+            _ => None,
+        }
+    }
+
+    /// A theme of the config or a built-in one, the themes of the config can only be based on built-in ones
+    pub fn named(name: &str, themes: &BTreeMap<String, CustomTheme>) -> Result<Theme, String> {
+        let custom = match themes.get(name) {
+            Some(custom) => custom,
+            None => {
+                return Theme::built_in(name).ok_or_else(|| {
+                    format!(
+                        "is not a theme, use one of [themes] or {}",
+                        BUILT_IN_THEMES.join(", ")
+                    )
+                })
+            }
+        };
+        let base = custom.base.as_deref().unwrap_or("dark");
+        let mut theme = Theme::built_in(base)
+            .ok_or_else(|| format!("'{}' is not one of {}", base, BUILT_IN_THEMES.join(", ")))?;
+        for (style, custom) in [
+            (&mut theme.text, &custom.text),
+            (&mut theme.title, &custom.title),
+            (&mut theme.accent, &custom.accent),
+            (&mut theme.key, &custom.key),
+            (&mut theme.header, &custom.header),
+            (&mut theme.selection, &custom.selection),
+            (&mut theme.field_selection, &custom.field_selection),
+            (&mut theme.status, &custom.status),
+            (&mut theme.warning, &custom.warning),
+            (&mut theme.error, &custom.error),
+            (&mut theme.input, &custom.input),
+            (&mut theme.cursor, &custom.cursor),
+        ] {
+            if let Some(StyleSpec(custom)) = custom {
+                *style = *custom;
+            }
+        }
+        Ok(theme)
+    }
+
+    /// The theme of the config, monochrome if `NO_COLOR` is set
+    pub fn active(settings: &Settings) -> Theme {
+        if env::var_os("NO_COLOR")
+            .map(|x| !x.is_empty())
+            .unwrap_or(false)
+        {
+            return Theme::monochrome();
+        }
+        settings
+            .theme
+            .as_deref()
+            .and_then(|name| Theme::named(name, &settings.themes).ok())
+            .unwrap_or_else(Theme::dark)
+    }
+}
+
+/// Theme of the config, every style which is left out is taken from `base`
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct CustomTheme {
+    pub base: Option<String>,
+    pub text: Option<StyleSpec>,
+    pub title: Option<StyleSpec>,
+    pub accent: Option<StyleSpec>,
+    pub key: Option<StyleSpec>,
+    pub header: Option<StyleSpec>,
+    pub selection: Option<StyleSpec>,
+    pub field_selection: Option<StyleSpec>,
+    pub status: Option<StyleSpec>,
+    pub warning: Option<StyleSpec>,
+    pub error: Option<StyleSpec>,
+    pub input: Option<StyleSpec>,
+    pub cursor: Option<StyleSpec>,
+}
+
+/// A style like `yellow`, `black on red bold` or `#8ec07c underlined`
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(try_from = "String")]
+pub struct StyleSpec(Style);
+
+impl TryFrom<String> for StyleSpec {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<StyleSpec, String> {
+        let mut style = Style::default();
+        let mut words = text.split_whitespace();
+        while let Some(word) = words.next() {
+            let word = word.to_lowercase();
+            if word == "on" {
+                let color = words
+                    .next()
+                    .ok_or_else(|| format!("missing color after 'on' in '{}'", text))?;
+                style = style.bg(parse_color(color)?);
+                continue;
+            }
+            style = match word.as_str() {
+                "bold" => style.add_modifier(Modifier::BOLD),
+                "dim" => style.add_modifier(Modifier::DIM),
+                "italic" => style.add_modifier(Modifier::ITALIC),
+                "underlined" => style.add_modifier(Modifier::UNDERLINED),
+                "reversed" => style.add_modifier(Modifier::REVERSED),
+                "blink" => style.add_modifier(Modifier::SLOW_BLINK),
+                color => style.fg(parse_color(color)?),
+            };
+        }
+        Ok(StyleSpec(style))
+    }
+}
+
+/// Names of the 16 terminal colors, `#rrggbb` or the number of one of the 256 colors
+fn parse_color(text: &str) -> Result<Color, String> {
+    let color = match text.to_lowercase().replace('_', "-").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "dark-gray" | "dark-grey" => Color::DarkGray,
+        "light-red" => Color::LightRed,
+        "light-green" => Color::LightGreen,
+        "light-yellow" => Color::LightYellow,
+        "light-blue" => Color::LightBlue,
+        "light-magenta" => Color::LightMagenta,
+        "light-cyan" => Color::LightCyan,
+        "white" => Color::White,
+        hex if hex.starts_with('#') && hex.len() == 7 => {
+            let channel = |range| u8::from_str_radix(&hex[range], 16);
+            match (channel(1..3), channel(3..5), channel(5..7)) {
+                (Ok(r), Ok(g), Ok(b)) => Color::Rgb(r, g, b),
+                _ => return Err(format!("'{}' is not a color", text)),
+            }
+        }
+        index => match index.parse() {
+            Ok(index) => Color::Indexed(index),
+            Err(_) => return Err(format!("'{}' is neither a color nor a style", text)),
+        },
+    };
+    Ok(color)
+}
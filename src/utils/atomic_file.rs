@@ -0,0 +1,40 @@
+use anyhow::{anyhow, Ok};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+/// Writes `content` into a temporary file next to `path`, syncs it to disk and renames it into place.
+/// A crash or a full disk leaves the old file untouched instead of a truncated one
+pub fn write_atomically(path: &Path, content: &[u8]) -> Result<(), anyhow::Error> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file", path.display()))?;
+    let temporary = path.with_file_name(format!(
+        ".{}.tmp{}",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    let result = write_and_rename(&temporary, path, content);
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    result
+}
+
+fn write_and_rename(temporary: &Path, path: &Path, content: &[u8]) -> Result<(), anyhow::Error> {
+    let mut file = File::create(temporary)?;
+    // the new file keeps the permissions of the one it replaces
+    if let std::result::Result::Ok(metadata) = fs::metadata(path) {
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.write_all(content)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(temporary, path)?;
+    // the rename itself is only durable once the directory is synced
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|x| !x.as_os_str().is_empty()) {
+        let _ = File::open(dir).and_then(|x| x.sync_all());
+    }
+    Ok(())
+}
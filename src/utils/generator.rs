@@ -0,0 +1,35 @@
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::settings::GeneratorSettings;
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!#$%&()*+,-./:;<=>?@[]^_{|}~";
+
+/// Random password with at least one character of every enabled class
+pub fn generate_password(settings: &GeneratorSettings) -> String {
+    let classes: Vec<&[u8]> = [
+        (settings.lowercase, LOWERCASE),
+        (settings.uppercase, UPPERCASE),
+        (settings.digits, DIGITS),
+        (settings.symbols, SYMBOLS),
+    ]
+    .iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, chars)| chars.as_bytes())
+    .collect();
+    let all: Vec<u8> = classes.concat();
+
+    let mut password: Vec<u8> = classes
+        .iter()
+        .map(|x| *x.choose(&mut OsRng).unwrap())
+        .collect();
+    while password.len() < settings.length {
+        password.push(all[OsRng.gen_range(0..all.len())]);
+    }
+    password.shuffle(&mut OsRng);
+    String::from_utf8(password).unwrap()
+}
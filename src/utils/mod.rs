@@ -1 +1,6 @@
+pub mod atomic_file;
+pub mod host;
+pub mod generator;
+pub mod registry;
+pub mod settings;
 pub mod terminal_interactions;
\ No newline at end of file
@@ -0,0 +1,125 @@
+use anyhow::{anyhow, Ok};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::atomic_file::write_atomically;
+use super::settings::config_dir;
+
+/// Vault which was created or opened before
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KnownVault {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub alias: Option<String>,
+    #[serde(default)]
+    pub last_opened: Option<DateTime<Local>>,
+}
+
+/// Paths of all known vaults, stored per user in `<config dir>/vault/vaults.json`
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Registry {
+    pub vaults: Vec<KnownVault>,
+}
+
+impl Registry {
+    fn path() -> Result<PathBuf, anyhow::Error> {
+        Ok(config_dir()?.join("vaults.json"))
+    }
+
+    /// An empty registry if no vault was recorded yet
+    pub fn load() -> Result<Registry, anyhow::Error> {
+        let path = Registry::path()?;
+        if !path.exists() {
+            return Ok(Registry::default());
+        }
+        serde_json::from_str(&fs::read_to_string(&path)?)
+            .map_err(|err| anyhow!("{} is not a valid vault registry: {}", path.display(), err))
+    }
+
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        let path = Registry::path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        write_atomically(&path, serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Remembers the vault as opened now, the path is stored absolute
+    pub fn record(&mut self, vault: &Path) -> Result<(), anyhow::Error> {
+        let path = fs::canonicalize(vault)?;
+        match self.vaults.iter_mut().find(|x| x.path == path) {
+            Some(known) => known.last_opened = Some(Local::now()),
+            None => self.vaults.push(KnownVault {
+                path,
+                alias: None,
+                last_opened: Some(Local::now()),
+            }),
+        }
+        Ok(())
+    }
+
+    /// Gives the vault an alias, which is taken away from any other vault
+    pub fn set_alias(&mut self, vault: &Path, alias: &str) -> Result<(), anyhow::Error> {
+        let path = fs::canonicalize(vault)?;
+        for known in self.vaults.iter_mut() {
+            if known.alias.as_deref() == Some(alias) {
+                known.alias = None;
+            }
+        }
+        match self.vaults.iter_mut().find(|x| x.path == path) {
+            Some(known) => known.alias = Some(alias.to_owned()),
+            None => self.vaults.push(KnownVault {
+                path,
+                alias: Some(alias.to_owned()),
+                last_opened: None,
+            }),
+        }
+        Ok(())
+    }
+
+    /// Returns false if no vault has the alias
+    pub fn remove_alias(&mut self, alias: &str) -> bool {
+        match self
+            .vaults
+            .iter_mut()
+            .find(|x| x.alias.as_deref() == Some(alias))
+        {
+            Some(known) => {
+                known.alias = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn find_alias(&self, alias: &str) -> Option<&KnownVault> {
+        self.vaults
+            .iter()
+            .find(|x| x.alias.as_deref() == Some(alias))
+    }
+
+    /// Forgets all vaults whose file doesn't exist anymore and returns them
+    pub fn prune(&mut self) -> Vec<KnownVault> {
+        let (kept, missing) = self.vaults.drain(..).partition(|x| x.path.exists());
+        self.vaults = kept;
+        missing
+    }
+}
+
+/// Records the vault in the registry. Failing to do so doesn't stop working with the vault
+pub fn remember_vault(vault: &Path) {
+    let result = Registry::load().and_then(|mut registry| {
+        registry.record(vault)?;
+        registry.save()
+    });
+    if let Err(err) = result {
+        eprintln!(
+            "Could not add {} to the known vaults: {}",
+            vault.display(),
+            err
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::{anyhow, Ok};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::encryption_and_decryption::argon::KdfParams;
+use crate::ui::keymap::{Action, KeyBindings, Keymap};
+use crate::ui::theme::{CustomTheme, Theme};
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// User configuration from `<config dir>/config.toml`, every key is optional
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct Settings {
+    /// Path or alias of the vault `open` uses without `-d`
+    pub default_vault: Option<String>,
+    /// Seconds after which a copied value is removed from the clipboard, 0 keeps it
+    pub clipboard_timeout: u64,
+    /// Seconds without input after which the open vault is locked, 0 never locks it
+    pub lock_timeout: u64,
+    /// Older versions of the vault file which are kept on every save
+    pub backup_count: u32,
+    pub generator: GeneratorSettings,
+    /// Used for new vaults, existing ones keep the parameters they were created with
+    pub kdf: KdfParams,
+    /// Keys of the GUI by action, an action listed here loses its default keys
+    pub keys: BTreeMap<Action, KeyBindings>,
+    /// Name of a built-in theme or one of `themes`
+    pub theme: Option<String>,
+    pub themes: BTreeMap<String, CustomTheme>,
+}
+
+/// Defaults of the password generator
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct GeneratorSettings {
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> GeneratorSettings {
+        GeneratorSettings {
+            length: 20,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/vault`, falls back to `~/.config/vault`
+pub fn config_dir() -> Result<PathBuf, anyhow::Error> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|x| !x.is_empty()) {
+        return Ok(PathBuf::from(dir).join("vault"));
+    }
+    let home = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .ok_or_else(|| anyhow!("neither XDG_CONFIG_HOME nor HOME is set"))?;
+    Ok(PathBuf::from(home).join(".config").join("vault"))
+}
+
+/// Reads the config once at start, an explicit `path` has to exist while the default one is optional
+pub fn load_settings(path: Option<&Path>) -> Result<(), anyhow::Error> {
+    let (path, required) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => match config_dir() {
+            std::result::Result::Ok(dir) => (dir.join("config.toml"), false),
+            Err(_) => return Ok(()),
+        },
+    };
+    if !required && !path.exists() {
+        return Ok(());
+    }
+    let text = fs::read_to_string(&path)
+        .map_err(|err| anyhow!("could not read the config {}: {}", path.display(), err))?;
+    let settings: Settings = toml::from_str(&text)
+        .map_err(|err| anyhow!("invalid config {}: {}", path.display(), err))?;
+    if let Err((key, reason)) = settings.validate() {
+        return Err(anyhow!(
+            "invalid config {}: `{}` {}",
+            path.display(),
+            key,
+            reason
+        ));
+    }
+    let _ = SETTINGS.set(settings);
+    Ok(())
+}
+
+/// The loaded config, the defaults if there is none
+pub fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
+
+impl Settings {
+    /// Returns the offending key and what is wrong with it
+    fn validate(&self) -> Result<(), (String, String)> {
+        if let Some(vault) = &self.default_vault {
+            if vault.trim().is_empty() {
+                return Err((
+                    String::from("default_vault"),
+                    String::from("must not be empty"),
+                ));
+            }
+        }
+        let generator = &self.generator;
+        if !(4..=1024).contains(&generator.length) {
+            return Err((
+                String::from("generator.length"),
+                String::from("must be between 4 and 1024"),
+            ));
+        }
+        if !(generator.lowercase || generator.uppercase || generator.digits || generator.symbols) {
+            return Err((
+                String::from("generator"),
+                String::from("needs at least one of lowercase, uppercase, digits or symbols"),
+            ));
+        }
+        if let Err((key, reason)) = self.kdf.validate() {
+            return Err((format!("kdf.{}", key), reason));
+        }
+        if let Some((action, reason)) = Keymap::new(&self.keys).conflict() {
+            return Err((format!("keys.{}", action.name()), reason));
+        }
+        for name in self.themes.keys() {
+            if let Err(reason) = Theme::named(name, &self.themes) {
+                return Err((format!("themes.{}.base", name), reason));
+            }
+        }
+        if let Some(theme) = &self.theme {
+            if let Err(reason) = Theme::named(theme, &self.themes) {
+                return Err((String::from("theme"), reason));
+            }
+        }
+        std::result::Result::Ok(())
+    }
+}
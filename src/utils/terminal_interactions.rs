@@ -1,26 +1,30 @@
 use std::io::{stdout, Write, stdin};
 
-use anyhow::Ok;
+use anyhow::{anyhow, Ok};
 
-pub fn prompt_user(question: &str) -> String{
+/// Reads one line, fails if the input is closed instead of returning empty answers forever
+pub fn prompt_user(question: &str) -> Result<String, anyhow::Error>{
     print!("{}", question);
     let mut answer = String::new(); 
     let _ = stdout().flush();
-    stdin().read_line(&mut answer).expect("This was not a correct string");
+    if stdin().read_line(&mut answer)? == 0 {
+        return Err(anyhow!("no answer, the input was closed"));
+    }
     if let Some('\n')=answer.chars().next_back() {
         answer.pop();
     }
     if let Some('\r')=answer.chars().next_back() {
         answer.pop();
     }
-    answer
+    Ok(answer)
 }
 
-pub fn prompt_password() -> Result<String, anyhow::Error>{
+/// Asks twice for a new password, `name` is shown in the prompt like "the password"
+pub fn prompt_password(name: &str) -> Result<String, anyhow::Error>{
     let mut password: String;
     loop {
-        password = rpassword::prompt_password("Please enter the password:")?;
-        if password != rpassword::prompt_password("Please re-enter the password:")?{
+        password = rpassword::prompt_password(format!("Please enter {}:", name))?;
+        if password != rpassword::prompt_password(format!("Please re-enter {}:", name))?{
             println!("Your passwords didn't match, pls try again!")
         } else {
             return Ok(password)